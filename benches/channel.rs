@@ -0,0 +1,98 @@
+//! Cost of moving resampled audio from the device callback to the collector thread, sending one
+//! message per sample versus one block per callback. Run with `cargo bench --bench channel`.
+//!
+//! "callback" is the time the audio thread spends handing off each callback's samples, which is
+//! what competes with the device deadline; "total" includes draining the collector.
+
+use std::{
+    hint::black_box,
+    time::{Duration, Instant},
+};
+
+use voice::{audio::AudioMessage, sync::ProcessNode};
+
+const SECONDS: usize = 60;
+const RATE: usize = 16_000;
+/// 16 kHz output of a 512-frame callback at 48 kHz
+const BLOCK: usize = 170;
+
+struct Timing {
+    total: Duration,
+    callback_mean: Duration,
+    callback_max: Duration,
+    received: usize,
+}
+
+fn per_sample(samples: &[f32]) -> Timing {
+    let node = ProcessNode::new(|it| it.collect::<Vec<f32>>());
+    let (sink, handle) = node.run();
+
+    let start = Instant::now();
+    let mut callbacks = Vec::new();
+    for block in samples.chunks(BLOCK) {
+        let callback = Instant::now();
+        for sample in block {
+            sink.send(*sample).unwrap();
+        }
+        callbacks.push(callback.elapsed());
+    }
+    drop(sink);
+    let received = black_box(handle.join().unwrap()).len();
+
+    timing(start.elapsed(), &callbacks, received)
+}
+
+fn blocks(samples: &[f32]) -> Timing {
+    let node = ProcessNode::new(|it| {
+        let mut audio = Vec::new();
+        for msg in it {
+            if let AudioMessage::Data(block) = msg {
+                audio.extend(block);
+            }
+        }
+        audio
+    });
+    let (sink, handle) = node.run();
+
+    let start = Instant::now();
+    let mut callbacks = Vec::new();
+    for block in samples.chunks(BLOCK) {
+        let callback = Instant::now();
+        sink.send(AudioMessage::<f32>::Data(block.to_vec()))
+            .unwrap();
+        callbacks.push(callback.elapsed());
+    }
+    drop(sink);
+    let received = black_box(handle.join().unwrap()).len();
+
+    timing(start.elapsed(), &callbacks, received)
+}
+
+fn timing(total: Duration, callbacks: &[Duration], received: usize) -> Timing {
+    Timing {
+        total,
+        callback_mean: callbacks.iter().sum::<Duration>() / callbacks.len() as u32,
+        callback_max: callbacks.iter().max().copied().unwrap_or_default(),
+        received,
+    }
+}
+
+fn report(name: &str, t: &Timing) {
+    println!(
+        "{name:>10}: total {:>10.2?} ({:>6.1}M samples/s), callback mean {:>9.2?} max {:>9.2?}, {} samples",
+        t.total,
+        t.received as f64 / t.total.as_secs_f64() / 1e6,
+        t.callback_mean,
+        t.callback_max,
+        t.received,
+    );
+}
+
+fn main() {
+    let samples: Vec<f32> = (0..SECONDS * RATE)
+        .map(|i| (i as f32 * 0.01).sin())
+        .collect();
+
+    report("per sample", &per_sample(&samples));
+    report("blocks", &blocks(&samples));
+}
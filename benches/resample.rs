@@ -0,0 +1,58 @@
+//! Throughput of the streaming resampler against the per-callback `dasp` sinc converter it
+//! replaced, at common device rates. Run with `cargo bench --bench resample`.
+
+use std::{hint::black_box, time::Instant};
+
+use dasp::{interpolate::sinc::Sinc, ring_buffer, Signal};
+use voice::audio::resample::Resampler;
+
+const SECONDS: u32 = 10;
+const CALLBACK_FRAMES: usize = 512;
+
+fn input(rate: u32) -> Vec<f32> {
+    (0..rate * SECONDS)
+        .map(|i| (f64::from(i) * 440.0 * std::f64::consts::TAU / f64::from(rate)).sin() as f32)
+        .collect()
+}
+
+fn streaming(rate: u32, input: &[f32]) -> usize {
+    let mut resampler = Resampler::new(rate, 16_000);
+    let mut out = Vec::new();
+    for chunk in input.chunks(CALLBACK_FRAMES) {
+        resampler.process(chunk, &mut out);
+    }
+    resampler.finish(&mut out);
+    black_box(&out).len()
+}
+
+fn dasp_per_callback(rate: u32, input: &[f32]) -> usize {
+    let mut out = Vec::new();
+    for chunk in input.chunks(CALLBACK_FRAMES) {
+        let interpolator = Sinc::new(ring_buffer::Fixed::from([0.0_f32; 128]));
+        let signal = dasp::signal::from_iter(chunk.iter().copied());
+        out.extend(
+            signal
+                .from_hz_to_hz(interpolator, f64::from(rate), 16_000.0)
+                .until_exhausted(),
+        );
+    }
+    black_box(&out).len()
+}
+
+fn report(name: &str, rate: u32, input: &[f32], run: fn(u32, &[f32]) -> usize) {
+    let start = Instant::now();
+    let produced = run(rate, input);
+    let elapsed = start.elapsed();
+    println!(
+        "{name:>18} {rate:>6} Hz: {elapsed:>10.2?} for {SECONDS}s of audio ({:>7.0}x realtime, {produced} samples)",
+        f64::from(SECONDS) / elapsed.as_secs_f64(),
+    );
+}
+
+fn main() {
+    for rate in [44_100, 48_000, 96_000] {
+        let input = input(rate);
+        report("streaming", rate, &input, streaming);
+        report("dasp per callback", rate, &input, dasp_per_callback);
+    }
+}
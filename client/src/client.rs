@@ -1,8 +1,14 @@
+use std::path::PathBuf;
+
 use voice::{
-    app::{response::Response, state::Mode},
+    app::{event::Event, response::Response, state::Mode},
+    audio::{dsp::DspConfig, vad, DeviceMatch, Downmix, Endpointing, Session},
     whisper::transcription::Model,
 };
 
+/// Silence that ends an utterance of a continuous recording, unless `--end-silence-ms` says.
+const UTTERANCE_SILENCE_MS: u32 = 700;
+
 #[derive(Debug, thiserror::Error)]
 pub enum Error {
     #[error("Client error: {0}")]
@@ -11,17 +17,97 @@ pub enum Error {
 
 #[derive(Debug, Clone, clap::Subcommand)]
 pub enum Commands {
+    #[command(group(clap::ArgGroup::new("auto_stop").multiple(true)))]
     Start {
         #[clap(short, long)]
         input_device: Option<String>,
 
+        /// How `--input-device` is matched against device names
+        #[clap(long, value_enum)]
+        device_match: Option<DeviceMatch>,
+
         #[clap(long)]
         sample_rate: Option<u32>,
 
         #[clap(short, long, value_enum)]
         model: Option<Model>,
+
+        /// Read audio from a WAV or FLAC file (on the server) instead of an input device
+        #[clap(long)]
+        input_file: Option<PathBuf>,
+
+        /// Only use these input channels, numbered from 1 (e.g. `--channels 3` or `--channels 1,2`)
+        #[clap(long, value_delimiter = ',')]
+        channels: Vec<u16>,
+
+        /// How to combine the selected channels into mono
+        #[clap(long, value_enum)]
+        downmix: Option<Downmix>,
+
+        /// Stop recording on its own after this many seconds
+        #[clap(long, group = "auto_stop")]
+        max_secs: Option<u32>,
+
+        /// Stop recording on its own once nobody has spoken for this many seconds
+        #[clap(long, group = "auto_stop")]
+        idle_secs: Option<u32>,
+
+        /// Processing to use instead of the server's defaults
+        #[clap(flatten)]
+        dsp: DspConfig,
+
+        /// Voice activity detection to use instead of the server's defaults
+        #[clap(flatten)]
+        vad: vad::Tuning,
+
+        /// Record through this audio host (see `hosts`) instead of the server's default
+        #[clap(long)]
+        audio_host: Option<String>,
+
+        /// Stop recording on its own once the speaker has been quiet for this many milliseconds,
+        /// or with `--continuous`, end the utterance (700 by default)
+        #[clap(long, group = "auto_stop")]
+        end_silence_ms: Option<u32>,
+
+        /// Shortest speech that counts towards `--end-silence-ms`, in milliseconds
+        #[clap(long, requires = "end_silence_ms")]
+        min_speech_ms: Option<u32>,
+
+        /// Wait for the recording to stop on its own, then print its transcription. Needs
+        /// `--max-secs`, `--idle-secs` or `--end-silence-ms`
+        #[clap(long, requires = "auto_stop")]
+        wait: bool,
+
+        /// Keep recording until `stop`, transcribing each utterance as soon as it ends (see
+        /// `events`)
+        #[clap(long, conflicts_with = "wait")]
+        continuous: bool,
     },
     Stop,
+    /// End the recording without transcribing it
+    Cancel,
+    /// Drop audio from the current recording until `resume`, keeping what came before
+    Pause,
+    Resume,
+    /// List the server's input devices
+    Devices {
+        /// List the devices of this audio host instead of the server's default
+        #[clap(long)]
+        audio_host: Option<String>,
+    },
+    /// List the audio hosts the server can record through
+    Hosts,
+    /// Print input levels and other events from the server as they happen
+    Events,
+    /// Print the transcription of each recording that stops on its own, such as those the
+    /// server's wake phrase starts
+    Listen,
+    /// Transcribe audio from before this command, if the server keeps a rolling capture
+    Recall {
+        /// How far back to go
+        #[clap(default_value_t = 30)]
+        secs: u32,
+    },
     Reset,
     ChangeMode {
         #[arg(value_enum)]
@@ -56,10 +142,116 @@ impl RunningApp {
         Ok(match self.command {
             Commands::Start {
                 input_device,
+                device_match,
                 sample_rate,
                 model,
-            } => self.client.start(input_device, sample_rate, model).await,
+                input_file,
+                channels,
+                downmix,
+                max_secs,
+                idle_secs,
+                dsp,
+                vad,
+                audio_host,
+                end_silence_ms,
+                min_speech_ms,
+                wait,
+                continuous,
+            } => {
+                let mut session = Session::new(input_device, sample_rate, None, model);
+                if let Some(device_match) = device_match {
+                    session = session.with_device_match(device_match);
+                }
+                if let Some(path) = input_file {
+                    session = session.with_input_file(path);
+                }
+                if !channels.is_empty() {
+                    session = session.with_channels(channels);
+                }
+                if let Some(downmix) = downmix {
+                    session = session.with_downmix(downmix);
+                }
+                if let Some(secs) = max_secs {
+                    session = session.with_max_secs(secs);
+                }
+                if let Some(secs) = idle_secs {
+                    session = session.with_idle_secs(secs);
+                }
+                if !dsp.is_empty() {
+                    session = session.with_dsp(dsp);
+                }
+                if !vad.is_empty() {
+                    session = session.with_vad(vad);
+                }
+                if let Some(host) = audio_host {
+                    session = session.with_host(host);
+                }
+                let end_silence_ms = match end_silence_ms {
+                    None if continuous => Some(UTTERANCE_SILENCE_MS),
+                    ms => ms,
+                };
+                if let Some(ms) = end_silence_ms {
+                    let mut endpointing = Endpointing::new(ms);
+                    if let Some(ms) = min_speech_ms {
+                        endpointing = endpointing.with_min_speech_ms(ms);
+                    }
+                    session = if continuous {
+                        session.with_continuous(endpointing)
+                    } else {
+                        session.with_endpointing(endpointing)
+                    };
+                }
+                if wait {
+                    self.client.start_and_wait(session).await
+                } else {
+                    self.client.start(session).await
+                }
+            }
             Commands::Stop => self.client.stop().await,
+            Commands::Cancel => self.client.cancel().await,
+            Commands::Pause => self.client.pause().await,
+            Commands::Resume => self.client.resume().await,
+            Commands::Devices { audio_host } => self.client.devices(audio_host.as_deref()).await,
+            Commands::Hosts => self.client.hosts().await,
+            Commands::Events => {
+                let mut events = self.client.subscribe().await?;
+                while let Some(event) = events.next().await? {
+                    println!("{event}");
+                }
+                Ok(Response::Nil)
+            }
+            Commands::Listen => {
+                let mut events = self.client.subscribe().await?;
+                // The recording the wake phrase started, if one is running
+                let mut woken = None;
+                while let Some(event) = events.next().await? {
+                    match event {
+                        Event::Wake(heard) => log::info!("Woken by \"{heard}\""),
+                        Event::Started {
+                            recording,
+                            wake: true,
+                            ..
+                        } => woken = Some(recording),
+                        Event::Stopped { recording, reason } if woken == Some(recording) => {
+                            woken = None;
+                            // Whoever stopped or cancelled it got the transcription instead
+                            if !reason.is_automatic() {
+                                continue;
+                            }
+                            if let Response::Transcription {
+                                content: Some(content),
+                                ..
+                            } = self.client.stop().await?
+                            {
+                                println!("{content}");
+                            }
+                        }
+                        _ => (),
+                    }
+                }
+                Ok(Response::Nil)
+            }
+            Commands::Recall { secs } => self.client.recall(secs).await,
             Commands::Reset => self.client.reset().await,
             Commands::ChangeMode { mode } => self.client.change_mode(mode).await,
         }?)
@@ -67,11 +259,12 @@ impl RunningApp {
 }
 
 pub mod api {
+    use std::collections::VecDeque;
+
     use serde::de::DeserializeOwned;
     use voice::{
-        app::{response::Response, state::Mode},
+        app::{event::Event, response::Response, state::Mode},
         audio::Session,
-        whisper::transcription::Model,
     };
 
     #[derive(Debug, thiserror::Error)]
@@ -83,7 +276,10 @@ pub mod api {
         Json(#[from] serde_json::Error),
 
         #[error("Unexpected response: {0}")]
-        UnexpectedResponse(Response),
+        UnexpectedResponse(Box<Response>),
+
+        #[error("The session never stops on its own, so there's nothing to wait for")]
+        NeverStops,
     }
 
     pub struct Client {
@@ -99,24 +295,96 @@ pub mod api {
             }
         }
 
-        pub async fn start(
-            &self,
-            input_device: Option<String>,
-            sample_rate: Option<u32>,
-            model: Option<Model>,
-        ) -> Result<Response, Error> {
-            let body = serde_json::to_value(Session::new(input_device, sample_rate, None, model))?
-                .to_string();
+        pub async fn start(&self, session: Session) -> Result<Response, Error> {
+            let body = serde_json::to_value(session)?.to_string();
             println!("body: {body}");
             let req = self.post("/voice/start").body(body).build()?;
             self.execute(req).await
         }
 
+        /// Starts a recording and waits for it to stop on its own, returning its transcription.
+        /// If it's stopped or cancelled some other way, there's no transcription to return.
+        pub async fn start_and_wait(&self, session: Session) -> Result<Response, Error> {
+            if !session.stops_itself() {
+                return Err(Error::NeverStops);
+            }
+            // Subscribed first, so the stop can't be missed
+            let mut events = self.subscribe().await?;
+            let started_at = match self.start(session).await? {
+                Response::Ack(at) => at,
+                r => return Ok(r),
+            };
+            let mut ours = None;
+            while let Some(event) = events.next().await? {
+                match event {
+                    Event::Started { recording, at, .. } if at == started_at => {
+                        ours = Some(recording);
+                    }
+                    Event::Stopped { recording, reason } if Some(recording) == ours => {
+                        log::info!("Recording stopped: {reason}");
+                        if !reason.is_automatic() {
+                            return Ok(Response::Nil);
+                        }
+                        break;
+                    }
+                    _ => (),
+                }
+            }
+            self.stop().await
+        }
+
         pub async fn stop(&self) -> Result<Response, Error> {
             let req = self.post("/voice/stop").build()?;
             self.execute(req).await
         }
 
+        pub async fn cancel(&self) -> Result<Response, Error> {
+            let req = self.post("/voice/cancel").build()?;
+            self.execute(req).await
+        }
+
+        pub async fn pause(&self) -> Result<Response, Error> {
+            let req = self.post("/voice/pause").build()?;
+            self.execute(req).await
+        }
+
+        pub async fn resume(&self) -> Result<Response, Error> {
+            let req = self.post("/voice/resume").build()?;
+            self.execute(req).await
+        }
+
+        pub async fn devices(&self, host: Option<&str>) -> Result<Response, Error> {
+            let mut req = self.inner.get(self.route("/voice/devices"));
+            if let Some(host) = host {
+                req = req.query(&[("host", host)]);
+            }
+            self.execute(req.build()?).await
+        }
+
+        pub async fn hosts(&self) -> Result<Response, Error> {
+            let req = self.inner.get(self.route("/voice/hosts")).build()?;
+            self.execute(req).await
+        }
+
+        /// Opens the server's event stream.
+        pub async fn subscribe(&self) -> Result<Events, Error> {
+            let req = self.inner.get(self.route("/voice/events")).build()?;
+            let resp = self.inner.execute(req).await?.error_for_status()?;
+            Ok(Events {
+                resp,
+                buffer: String::new(),
+                queued: VecDeque::new(),
+            })
+        }
+
+        pub async fn recall(&self, secs: u32) -> Result<Response, Error> {
+            let req = self
+                .post("/voice/recall")
+                .body(serde_json::json!({ "secs": secs }).to_string())
+                .build()?;
+            self.execute(req).await
+        }
+
         pub async fn reset(&self) -> Result<Response, Error> {
             let req = self.post("/voice/reset").build()?;
             self.execute(req).await
@@ -150,4 +418,30 @@ pub mod api {
             Ok(serde_json::from_str(&r.text().await?)?)
         }
     }
+
+    /// Events from the server, in the order it sent them.
+    pub struct Events {
+        resp: reqwest::Response,
+        buffer: String,
+        queued: VecDeque<Event>,
+    }
+
+    impl Events {
+        /// Waits for the next event, or `None` once the server closes the stream.
+        pub async fn next(&mut self) -> Result<Option<Event>, Error> {
+            while self.queued.is_empty() {
+                let Some(chunk) = self.resp.chunk().await? else {
+                    return Ok(None);
+                };
+                self.buffer.push_str(&String::from_utf8_lossy(&chunk));
+                while let Some(end) = self.buffer.find("\n\n") {
+                    let message: String = self.buffer.drain(..end + 2).collect();
+                    for data in message.lines().filter_map(|l| l.strip_prefix("data: ")) {
+                        self.queued.push_back(serde_json::from_str(data)?);
+                    }
+                }
+            }
+            Ok(self.queued.pop_front())
+        }
+    }
 }
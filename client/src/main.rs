@@ -2,7 +2,10 @@ mod client;
 
 use clap::Parser;
 use client::{App, Commands, RunningApp};
-use voice::app::response::Response;
+use voice::{
+    app::response::Response,
+    audio::{dbfs, LevelSummary, StopReason},
+};
 
 #[tokio::main]
 async fn main() -> Result<(), client::Error> {
@@ -10,23 +13,67 @@ async fn main() -> Result<(), client::Error> {
 
     let app = App::parse();
     match &app.command {
-        Commands::Start { .. } => {
+        Commands::Start { wait: true, .. } | Commands::Stop | Commands::Recall { .. } => {
             match RunningApp::from(app).execute().await? {
-                Response::Ack(_) => (),
-                r => return Err(client::api::Error::UnexpectedResponse(r).into()),
-            }
-            Ok(())
-        }
-        Commands::Stop => {
-            match RunningApp::from(app).execute().await? {
-                Response::Transcription { content, .. } => {
+                Response::Transcription {
+                    content,
+                    fault,
+                    stop_reason,
+                    levels,
+                    recording,
+                    ..
+                } => {
+                    if let Some(recording) = recording {
+                        log::info!("Recorded {recording}");
+                    }
+                    if let Some(levels) = levels.filter(LevelSummary::is_silent) {
+                        eprintln!(
+                            "Input was silent ({:.1} dBFS), is the right device selected?",
+                            dbfs(levels.rms())
+                        );
+                    }
+                    if let Some(fault) = fault {
+                        eprintln!("Recording interrupted: {fault:?}");
+                    }
+                    if let Some(reason) = stop_reason.filter(|r| *r != StopReason::Requested) {
+                        eprintln!("Recording stopped earlier: {reason}");
+                    }
                     let Some(content) = content else {
                         eprintln!("No transcription available");
                         return Ok(());
                     };
                     println!("{content}");
                 }
-                r => return Err(client::api::Error::UnexpectedResponse(r).into()),
+                r => return Err(client::api::Error::UnexpectedResponse(Box::new(r)).into()),
+            }
+            Ok(())
+        }
+        Commands::Start { .. } | Commands::Cancel | Commands::Pause | Commands::Resume => {
+            match RunningApp::from(app).execute().await? {
+                Response::Ack(_) => (),
+                r => return Err(client::api::Error::UnexpectedResponse(Box::new(r)).into()),
+            }
+            Ok(())
+        }
+        Commands::Devices { .. } => {
+            match RunningApp::from(app).execute().await? {
+                Response::Devices(devices) => {
+                    for device in devices {
+                        println!("{device}");
+                    }
+                }
+                r => return Err(client::api::Error::UnexpectedResponse(Box::new(r)).into()),
+            }
+            Ok(())
+        }
+        Commands::Hosts => {
+            match RunningApp::from(app).execute().await? {
+                Response::Hosts(hosts) => {
+                    for host in hosts {
+                        println!("{host}");
+                    }
+                }
+                r => return Err(client::api::Error::UnexpectedResponse(Box::new(r)).into()),
             }
             Ok(())
         }
@@ -4,7 +4,7 @@ mod web;
 
 use clap::Parser;
 use tokio::task::spawn_blocking;
-use voice::app::{Daemon, DaemonInit};
+use voice::app::{transcribe::TranscribeInit, Daemon, DaemonInit};
 
 #[derive(Debug, clap::Parser)]
 #[command(version, about, long_about = None)]
@@ -31,6 +31,9 @@ impl ServeDaemonOpts {
 #[derive(Debug, clap::Subcommand)]
 enum Commands {
     RunDaemon(ServeDaemonOpts),
+
+    /// Transcribe raw interleaved PCM from stdin or a named pipe
+    Transcribe(TranscribeInit),
 }
 
 #[derive(Debug, thiserror::Error)]
@@ -47,13 +50,12 @@ async fn run_web_server(app: ServeDaemonOpts) -> std::io::Result<bool> {
     let (responses_out, responses_in) = crossbeam::channel::bounded(1);
 
     let addr = app.serve_addr();
-    let handle = spawn_blocking(move || {
-        log::info!("Launching with settings: {:?}", app);
-        let mut daemon = Daemon::new(app.delegate);
-        daemon.run_loop(commands_in, responses_out)
-    });
+    log::info!("Launching with settings: {:?}", app);
+    let mut daemon = Daemon::new(app.delegate);
+    let events = daemon.events();
+    let handle = spawn_blocking(move || daemon.run_loop(commands_in, responses_out));
 
-    let server = web::Server::new(addr, commands_out, responses_in);
+    let server = web::Server::new(addr, commands_out, responses_in, events);
     let server_handle = server.run();
 
     tokio::select! {
@@ -79,5 +81,12 @@ async fn main() -> Result<(), Error> {
             }
             Ok(())
         }
+        Commands::Transcribe(opts) => {
+            match opts.run()? {
+                Some(content) => println!("{content}"),
+                None => eprintln!("No transcription available"),
+            }
+            Ok(())
+        }
     }
 }
@@ -1,15 +1,18 @@
 use actix_web::{
     body::BoxBody,
+    get,
     middleware::Logger,
     post,
     web::{self, Data},
     App, HttpRequest, HttpResponse, HttpServer, Responder,
 };
 use crossbeam::channel::{Receiver, Sender};
-use serde::Serialize;
+use serde::{Deserialize, Serialize};
+use tokio::sync::broadcast;
 use voice::{
-    app::{command::Command, response::Response, state::Mode},
+    app::{command::Command, event::Event, response::Response, state::Mode},
     audio::Session,
+    sync::Broadcast,
 };
 
 struct ApiResponder<T> {
@@ -26,7 +29,11 @@ impl<T: Serialize> Responder for ApiResponder<T> {
 
 type AppChannel = web::Data<AppEvents>;
 
-struct AppEvents(Sender<Command>, Receiver<Response>);
+struct AppEvents(
+    Sender<Command>,
+    Receiver<Response>,
+    broadcast::Sender<Event>,
+);
 
 impl AppEvents {
     fn start(&self, session: Session) -> Response {
@@ -37,10 +44,38 @@ impl AppEvents {
         self.request(Command::Stop)
     }
 
+    fn cancel(&self) -> Response {
+        self.request(Command::Cancel)
+    }
+
+    fn pause(&self) -> Response {
+        self.request(Command::Pause)
+    }
+
+    fn resume(&self) -> Response {
+        self.request(Command::Resume)
+    }
+
+    fn recall(&self, secs: u32) -> Response {
+        self.request(Command::Recall(secs))
+    }
+
+    fn devices(&self, host: Option<String>) -> Response {
+        self.request(Command::Devices(host))
+    }
+
+    fn hosts(&self) -> Response {
+        self.request(Command::Hosts)
+    }
+
     fn mode(&self, mode: Mode) -> Response {
         self.request(Command::Mode(mode))
     }
 
+    fn subscribe(&self) -> broadcast::Receiver<Event> {
+        self.2.subscribe()
+    }
+
     fn request(&self, cmd: Command) -> Response {
         self.0.send(cmd).unwrap();
         self.1.recv().unwrap()
@@ -59,16 +94,90 @@ async fn stop(app: AppChannel) -> impl Responder {
     ApiResponder { content: response }
 }
 
+#[post("/cancel")]
+async fn cancel(app: AppChannel) -> impl Responder {
+    let response = app.cancel();
+    ApiResponder { content: response }
+}
+
+#[post("/pause")]
+async fn pause(app: AppChannel) -> impl Responder {
+    let response = app.pause();
+    ApiResponder { content: response }
+}
+
+#[post("/resume")]
+async fn resume(app: AppChannel) -> impl Responder {
+    let response = app.resume();
+    ApiResponder { content: response }
+}
+
+#[derive(Debug, Deserialize)]
+struct RecallRequest {
+    secs: u32,
+}
+
+#[post("/recall")]
+async fn recall(app: AppChannel, request: web::Json<RecallRequest>) -> impl Responder {
+    let response = app.recall(request.secs);
+    ApiResponder { content: response }
+}
+
+#[derive(Debug, Deserialize)]
+struct DevicesQuery {
+    host: Option<String>,
+}
+
+#[get("/devices")]
+async fn devices(app: AppChannel, query: web::Query<DevicesQuery>) -> impl Responder {
+    let response = app.devices(query.into_inner().host);
+    ApiResponder { content: response }
+}
+
+#[get("/hosts")]
+async fn hosts(app: AppChannel) -> impl Responder {
+    let response = app.hosts();
+    ApiResponder { content: response }
+}
+
+/// Streams daemon events as server-sent events until the client disconnects.
+#[get("/events")]
+async fn stream_events(app: AppChannel) -> impl Responder {
+    let stream = futures_util::stream::unfold(app.subscribe(), |mut recv| async move {
+        let event = loop {
+            match recv.recv().await {
+                Ok(event) => break event,
+                Err(broadcast::error::RecvError::Lagged(n)) => {
+                    log::warn!("Event stream fell behind, skipped {n} events");
+                }
+                Err(broadcast::error::RecvError::Closed) => return None,
+            }
+        };
+        let json = serde_json::to_string(&event).ok()?;
+        Some((
+            Ok::<_, actix_web::Error>(web::Bytes::from(format!("data: {json}\n\n"))),
+            recv,
+        ))
+    });
+    HttpResponse::Ok()
+        .content_type("text/event-stream")
+        .streaming(stream)
+}
+
 #[post("/mode")]
 async fn set_mode(app: AppChannel, mode: web::Json<Mode>) -> impl Responder {
     let response = app.mode(mode.into_inner());
     ApiResponder { content: response }
 }
 
+/// How many events an event stream can fall behind by before it starts missing them.
+const EVENT_BACKLOG: usize = 256;
+
 pub struct Server {
     addr: (String, u16),
     commands: Sender<Command>,
     responses: Receiver<Response>,
+    events: Broadcast<Event>,
 }
 
 impl Server {
@@ -77,23 +186,45 @@ impl Server {
         addr: (String, u16),
         commands: Sender<Command>,
         responses: Receiver<Response>,
+        events: Broadcast<Event>,
     ) -> Self {
         Self {
             addr,
             commands,
             responses,
+            events,
         }
     }
 
     pub async fn run(self) -> std::io::Result<()> {
+        // The daemon's broadcast is blocking, so one thread relays it to an async one that every
+        // event stream subscribes to, and a client going away just drops its receiver
+        let (events, _) = broadcast::channel(EVENT_BACKLOG);
+        let relay = events.clone();
+        let daemon_events = self.events.subscribe();
+        std::thread::spawn(move || {
+            for event in daemon_events {
+                // Fails only while nobody is streaming events
+                let _ = relay.send(event);
+            }
+        });
+
         let server = HttpServer::new(move || {
             let voice = web::scope("/voice")
                 .service(start)
                 .service(stop)
+                .service(cancel)
+                .service(pause)
+                .service(resume)
+                .service(recall)
+                .service(devices)
+                .service(hosts)
+                .service(stream_events)
                 .service(set_mode)
                 .app_data(Data::new(AppEvents(
                     self.commands.clone(),
                     self.responses.clone(),
+                    events.clone(),
                 )));
 
             App::new().wrap(Logger::default()).service(voice)
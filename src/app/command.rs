@@ -1,12 +1,12 @@
-use crossbeam::channel::Receiver;
+use crossbeam::channel::{select, unbounded, Receiver, Sender};
 
 use super::{
     response::Response,
     state::{Mode, State},
 };
-use crate::audio::Session;
+use crate::audio::{Session, StopReason};
 
-#[derive(Debug, Clone, Eq, PartialEq, serde::Serialize, serde::Deserialize)]
+#[derive(Debug, Clone, PartialEq, serde::Serialize, serde::Deserialize)]
 #[serde(tag = "type", content = "data")]
 pub enum Command {
     #[serde(rename = "start")]
@@ -15,6 +15,28 @@ pub enum Command {
     #[serde(rename = "stop")]
     Stop, // need timestamp?
 
+    /// Keep the recording's stream open but drop its audio until resumed
+    #[serde(rename = "pause")]
+    Pause,
+
+    #[serde(rename = "resume")]
+    Resume,
+
+    /// End the recording without transcribing it
+    #[serde(rename = "cancel")]
+    Cancel,
+
+    /// Transcribe the last this many seconds of rolling capture
+    #[serde(rename = "recall")]
+    Recall(u32),
+
+    /// List the input devices of this audio host, or of the daemon's default one
+    #[serde(rename = "devices")]
+    Devices(Option<String>),
+
+    #[serde(rename = "hosts")]
+    Hosts,
+
     #[serde(rename = "reset")]
     Reset,
 
@@ -23,6 +45,16 @@ pub enum Command {
 
     #[serde(rename = "respond")]
     Respond(Response),
+
+    /// Sent by the daemon to itself when the recording it numbered stops on its own. Never
+    /// answered, since nobody is waiting on it.
+    #[serde(skip)]
+    AutoStop(u64, StopReason),
+
+    /// Sent by the daemon to itself to start `session` once it hears the wake phrase. Works
+    /// like [`Command::Start`], but isn't answered.
+    #[serde(skip)]
+    Wake(Session),
 }
 
 impl Command {
@@ -36,16 +68,35 @@ impl Command {
     }
 }
 
-pub struct CmdStream(Receiver<Command>);
+/// Commands from clients, interleaved with any the daemon sends itself.
+pub struct CmdStream {
+    external: Receiver<Command>,
+    internal: (Sender<Command>, Receiver<Command>),
+}
 
 impl CmdStream {
     #[must_use]
     pub fn new(recv: Receiver<Command>) -> Self {
-        Self(recv)
+        Self {
+            external: recv,
+            internal: unbounded(),
+        }
+    }
+
+    /// For commands that don't come from a client, e.g. [`Command::AutoStop`].
+    #[must_use]
+    pub fn sender(&self) -> Sender<Command> {
+        self.internal.0.clone()
     }
 
+    /// Ends when the client channel is closed.
     pub fn iter(&mut self) -> impl Iterator<Item = Command> + '_ {
-        self.0.iter()
+        std::iter::from_fn(move || {
+            select! {
+                recv(self.external) -> cmd => cmd.ok(),
+                recv(self.internal.1) -> cmd => cmd.ok(),
+            }
+        })
     }
 
     pub fn run_state_machine<'a>(
@@ -53,16 +104,36 @@ impl CmdStream {
         state: &'a mut super::state::State,
     ) -> impl Iterator<Item = (Command, Option<State>)> + 'a {
         self.iter().map(move |cmd| {
-            log::debug!("Received command: {:?}", cmd);
-            log::trace!("Current state: {:?}", state);
-            let initial = state.clone();
-            if state.next_state(&cmd) {
-                log::trace!("State transitioned to {:?}", state);
-                (cmd, Some(state.clone()))
-            } else {
-                log::trace!("No state transition from {:?}", initial);
-                (cmd, None)
-            }
+            let new_state = Self::transition(state, &cmd);
+            (cmd, new_state)
         })
     }
+
+    /// Applies `cmd` to `state`, returning the new state if it changed.
+    pub fn transition(state: &mut State, cmd: &Command) -> Option<State> {
+        log::debug!("Received command: {:?}", cmd);
+        log::trace!("Current state: {:?}", state);
+        let initial = state.clone();
+        if state.next_state(cmd) {
+            log::trace!("State transitioned to {:?}", state);
+            Some(state.clone())
+        } else {
+            log::trace!("No state transition from {:?}", initial);
+            None
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_devices_host_is_optional() {
+        let command: Command = serde_json::from_str(r#"{"type":"devices"}"#).unwrap();
+        assert_eq!(command, Command::Devices(None));
+
+        let command: Command = serde_json::from_str(r#"{"type":"devices","data":"JACK"}"#).unwrap();
+        assert_eq!(command, Command::Devices(Some("JACK".to_string())));
+    }
 }
@@ -0,0 +1,74 @@
+use crate::audio::{Level, StopReason};
+
+/// Something the daemon reports as it happens rather than in reply to a command.
+#[derive(Debug, Clone, PartialEq, serde::Serialize, serde::Deserialize)]
+#[serde(tag = "type", content = "data")]
+pub enum Event {
+    /// Input level over the last window of the current recording
+    #[serde(rename = "level")]
+    Level(Level),
+
+    /// A recording started, numbered so its stop can be told apart from any other's. `at` is
+    /// the time in the start command's acknowledgement, and `wake` whether the wake phrase
+    /// started it instead.
+    #[serde(rename = "started")]
+    Started {
+        recording: u64,
+        at: u128,
+        wake: bool,
+    },
+
+    /// The recording numbered `recording` ended. If it stopped on its own, its transcription
+    /// is ready for the next stop.
+    #[serde(rename = "stopped")]
+    Stopped { recording: u64, reason: StopReason },
+
+    /// One utterance of a continuous recording, transcribed
+    #[serde(rename = "transcription")]
+    Transcription(String),
+
+    /// The wake phrase was heard, as transcribed, and a recording started
+    #[serde(rename = "wake")]
+    Wake(String),
+}
+
+impl std::fmt::Display for Event {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            Self::Level(level) => write!(f, "LEVEL {level}"),
+            Self::Started { recording, .. } => write!(f, "STARTED {recording}"),
+            Self::Stopped { recording, reason } => write!(f, "STOPPED {recording} {reason}"),
+            Self::Transcription(content) => write!(f, "TRANSCRIPTION {content}"),
+            Self::Wake(heard) => write!(f, "WAKE {heard}"),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::audio::LevelMeter;
+
+    #[test]
+    fn test_serialize_level() {
+        let mut meter = LevelMeter::new();
+        let level = meter.push(&[0.5; 800])[0];
+        let event = Event::Level(level);
+        let expected =
+            r#"{"type":"level","data":{"rms":0.5,"peak":0.5,"clipped":false,"silent":false}}"#;
+        assert_eq!(serde_json::to_string(&event).unwrap(), expected);
+        assert_eq!(serde_json::from_str::<Event>(expected).unwrap(), event);
+    }
+
+    #[test]
+    fn test_serialize_stopped() {
+        let event = Event::Stopped {
+            recording: 3,
+            reason: StopReason::EndOfSpeech,
+        };
+        let expected =
+            r#"{"type":"stopped","data":{"recording":3,"reason":{"type":"end_of_speech"}}}"#;
+        assert_eq!(serde_json::to_string(&event).unwrap(), expected);
+        assert_eq!(serde_json::from_str::<Event>(expected).unwrap(), event);
+    }
+}
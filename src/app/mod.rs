@@ -1,20 +1,39 @@
 pub mod command;
+pub mod event;
 pub mod response;
 pub mod state;
+pub mod transcribe;
+pub mod wake;
 
-use std::{path::PathBuf, time::SystemTime};
+use std::{
+    path::PathBuf,
+    thread::JoinHandle,
+    time::{Duration, SystemTime},
+};
 
-use crossbeam::channel::{unbounded, Receiver, SendError, Sender};
+use crossbeam::channel::{
+    bounded, select, unbounded, Receiver, SendError, Sender, TryRecvError, TrySendError,
+};
 use sttx::IteratorExt;
 
 use self::{
     command::{CmdStream, Command},
-    response::Response,
+    event::Event,
+    response::{Fault, Response},
+    wake::WakePhrase,
 };
 use crate::{
-    audio::{self, AudioMessage, Recording, RecordingError, Session},
-    sync,
-    whisper::{self, transcription::Job},
+    archive::{self, Archive, Sidecar},
+    audio::{
+        self, dsp::DspConfig, vad, AudioMessage, AutoStop, Captured, Endpointing, Gate, LevelMeter,
+        LevelSummary, Metadata, Monitor, Recording, RecordingError, Segmenter, Session,
+        SpeechClips, StopReason, OUTPUT_SAMPLE_RATE,
+    },
+    sync::{self, Broadcast},
+    whisper::{
+        self,
+        transcription::{Job, Model, TranscribeResult},
+    },
 };
 
 #[derive(Debug, thiserror::Error)]
@@ -31,6 +50,9 @@ pub enum Error {
     #[error("Recording error: {0}")]
     Recording(#[from] RecordingError),
 
+    #[error("PCM input error: {0}")]
+    Pcm(#[from] audio::pcm::Error),
+
     #[error("Socket/IO error: {0}")]
     Socket(#[from] std::io::Error),
 }
@@ -44,6 +66,9 @@ impl<T> From<SendError<T>> for Error {
 pub struct Daemon {
     config: DaemonInit,
     state: state::State,
+    events: Broadcast<Event>,
+    /// Transcribes the current recording's utterances, if it's continuous
+    dictation: Option<Dictation>,
 }
 
 #[derive(Debug, clap::Args)]
@@ -57,6 +82,52 @@ pub struct DaemonInit {
     /// Socket path
     #[clap(long)]
     socket_path: Option<String>,
+
+    /// Keep every recording as a 16 kHz WAV plus a JSON sidecar in this directory
+    #[clap(long)]
+    archive_dir: Option<PathBuf>,
+
+    /// Keep the default input device open and start each recording with this many
+    /// milliseconds of the audio captured before it
+    #[clap(long)]
+    preroll_ms: Option<usize>,
+
+    /// Keep the last this many seconds of the default input device so `recall` can
+    /// transcribe them after the fact
+    #[clap(long)]
+    rolling_secs: Option<usize>,
+
+    /// Processing for sessions that don't ask for their own
+    #[clap(flatten)]
+    dsp: DspConfig,
+
+    /// Audio host for sessions that don't pick one, e.g. "jack" (see `hosts`)
+    #[clap(long)]
+    audio_host: Option<String>,
+
+    /// Silence to keep either side of the speech when trimming recordings, in milliseconds
+    #[clap(long, default_value_t = 300)]
+    trim_margin_ms: u64,
+
+    /// Transcribe recordings whole, without trimming silence or skipping those with no speech
+    #[clap(long)]
+    no_trim: bool,
+
+    /// Voice activity detection for sessions that don't adjust their own
+    #[clap(flatten)]
+    vad: vad::Tuning,
+
+    /// Keep the default input device open and start recording whenever someone says this
+    #[clap(long)]
+    wake_phrase: Option<String>,
+
+    /// Model that listens for the wake phrase (base by default, for speed)
+    #[clap(long, value_enum)]
+    wake_model: Option<Model>,
+
+    /// Silence that ends a recording started by the wake phrase, in milliseconds
+    #[clap(long, default_value_t = 1500)]
+    wake_end_silence_ms: u32,
 }
 
 impl DaemonInit {
@@ -69,6 +140,77 @@ impl DaemonInit {
     pub fn socket_path(&self) -> Option<&str> {
         self.socket_path.as_deref()
     }
+
+    #[must_use]
+    pub fn archive(&self) -> Option<Archive> {
+        self.archive_dir.clone().map(Archive::new)
+    }
+
+    /// Opens the input monitor if pre-roll, rolling capture or a wake phrase was asked for.
+    /// Failing to open it only costs those features, so the error is logged rather than
+    /// returned.
+    #[must_use]
+    pub fn monitor(&self) -> Option<Monitor> {
+        let preroll_ms = self.preroll_ms.unwrap_or_default();
+        let rolling_ms = self.rolling_secs.unwrap_or_default() * 1000;
+        if preroll_ms == 0 && rolling_ms == 0 && self.wake_phrase.is_none() {
+            return None;
+        }
+        let session = match self.audio_host {
+            Some(ref host) => Session::default().with_host(host.clone()),
+            None => Session::default(),
+        };
+        Monitor::open(session, preroll_ms, rolling_ms)
+            .inspect_err(|e| log::error!("Failed to open input monitor: {e}"))
+            .ok()
+    }
+
+    /// The session the wake phrase starts, which stops once the speaker has finished.
+    fn wake_session(&self) -> Session {
+        Session::default().with_endpointing(Endpointing::new(self.wake_end_silence_ms))
+    }
+
+    /// Voice activity detection for `session`, falling back on the daemon's.
+    fn vad(&self, session: Option<&Session>) -> vad::Config {
+        session.and_then(Session::vad).unwrap_or(self.vad).config()
+    }
+
+    /// How to trim recordings of `session`.
+    fn trimming(&self, session: Option<&Session>) -> Trim {
+        Trim {
+            vad: self.vad(session),
+            margin: (!self.no_trim).then(|| Duration::from_millis(self.trim_margin_ms)),
+        }
+    }
+
+    /// Trims the silence around the speech in `audio`, or returns `None` when there's no
+    /// speech worth sending to whisper.
+    fn trim(&self, audio: Vec<f32>, session: Option<&Session>) -> Option<Vec<f32>> {
+        self.trimming(session).apply(audio)
+    }
+
+    fn write_sidecar(
+        &self,
+        entry: &archive::Entry,
+        state: &state::State,
+        metadata: &Metadata,
+        transcript: Option<&str>,
+    ) {
+        let session = state.session();
+        if session.is_none() {
+            log::warn!("Lost track of the recording's session, archiving it without");
+        }
+        let strategy = self.strategy.clone().unwrap_or_default();
+        let sidecar = Sidecar {
+            transcript,
+            ..Sidecar::new(session, metadata, &strategy)
+        };
+
+        match entry.write_sidecar(&sidecar) {
+            Ok(()) => log::debug!("Archived recording to {}", entry.audio_path().display()),
+            Err(e) => log::error!("Failed to write recording sidecar: {e}"),
+        }
+    }
 }
 
 impl Daemon {
@@ -77,9 +219,17 @@ impl Daemon {
         Self {
             config,
             state: state::State::default(),
+            events: Broadcast::default(),
+            dictation: None,
         }
     }
 
+    /// Where the daemon reports what's happening during a recording.
+    #[must_use]
+    pub fn events(&self) -> Broadcast<Event> {
+        self.events.clone()
+    }
+
     /// Runs the main application loop.
     #[allow(clippy::missing_panics_doc)]
     #[allow(clippy::too_many_lines)]
@@ -91,101 +241,251 @@ impl Daemon {
         let (to_whisper, from_recordings) = unbounded();
         let (whisper_output, tx_worker) =
             whisper::transcription_worker(self.config.model_dir.as_path(), from_recordings)?;
+        let worker = Worker::spawn(to_whisper, whisper_output);
 
         let mut commands = CmdStream::new(commands);
+        let to_daemon = commands.sender();
+        let monitor = self.config.monitor();
+        // Holds back audio from the wake phrase listener during a recording
+        let listening = Gate::default();
+        let mut wake_check = None;
+        if let Some(phrase) = self.config.wake_phrase.as_deref() {
+            if let Some(ref monitor) = monitor {
+                let check =
+                    self.start_wake_check(WakePhrase::new(phrase), &worker, to_daemon.clone());
+                let (sink, _) =
+                    listener(self.config.vad(None), listening.clone(), check.sender()).run();
+                monitor.listen(move |msg| sink.send(msg).is_ok());
+                wake_check = Some(check);
+                log::info!("Listening for the wake phrase");
+            } else {
+                log::error!("Can't listen for the wake phrase without the input monitor");
+            }
+        }
 
         let mut exit_code = 0_u8;
-        let mut rec: Option<Recording<_, Vec<f32>>> = None;
-        for (ref command, ref new_state) in commands.run_state_machine(&mut self.state) {
-            let Some(new_state) = new_state else {
-                responses.send(Response::Nil)?;
+        let mut rec: Option<Recording<f32, Collected>> = None;
+        // Drops the current recording's audio while it's paused
+        let mut gate = Gate::default();
+        // Numbers recordings so an auto-stop can't end a later one than it was meant for
+        let mut generation = 0_u64;
+        // Transcription of a recording that stopped on its own, for the next stop command
+        let mut pending: Option<Response> = None;
+        for command in commands.iter() {
+            let command = &command;
+            if matches!(command, Command::AutoStop(id, _) if *id != generation) {
+                continue;
+            }
+            let Some(ref new_state) = CmdStream::transition(&mut self.state, command) else {
+                match command {
+                    Command::AutoStop(..) | Command::Wake(_) => (),
+                    Command::Stop => responses.send(pending.take().unwrap_or(Response::Nil))?,
+                    // Also throws away a recording that already stopped on its own
+                    Command::Cancel if pending.take().is_some() => {
+                        responses.send(Response::ack())?;
+                    }
+                    _ => responses.send(Response::Nil)?,
+                }
                 continue;
             };
 
             match command {
-                Command::Start(session) => {
+                Command::Start(session) | Command::Wake(session) => {
                     assert!(new_state.running());
+                    // Only a client's start is waiting on an answer
+                    let reply = |response: Response| -> Result<(), Error> {
+                        match (command, response) {
+                            (Command::Start(_), response) => responses.send(response)?,
+                            (_, Response::Error(e)) => {
+                                log::error!("Failed to start recording on the wake phrase: {e}");
+                            }
+                            _ => (),
+                        }
+                        Ok(())
+                    };
+                    if pending.take().is_some() {
+                        log::warn!(
+                            "Discarding the transcription of an automatically stopped recording"
+                        );
+                    }
 
-                    let new_rec = match Recording::<_, _, audio::RecordingError>::controlled(
-                        session.clone(),
-                        sync::ProcessNode::new(|it| {
-                            it.map(|msg| match msg {
-                                AudioMessage::Data(data) => data,
-                                AudioMessage::Error(e) => panic!("{e}"),
-                            })
-                            .collect::<Vec<_>>()
-                        }),
+                    generation += 1;
+                    gate = Gate::default();
+                    let dictation = session
+                        .continuous()
+                        .map(|_| self.start_dictation(session, &worker));
+                    let collector = collector(
+                        generation,
+                        session,
+                        self.config.vad(Some(session)),
+                        gate.clone(),
+                        to_daemon.clone(),
+                        dictation.as_ref().map(Dictation::sender),
+                        self.events.clone(),
+                    );
+
+                    let mut session = match session.dsp() {
+                        Some(_) => session.clone(),
+                        None => session.clone().with_dsp(self.config.dsp),
+                    };
+                    if let (None, Some(host)) = (session.host(), &self.config.audio_host) {
+                        session = session.with_host(host.clone());
+                    }
+                    let new_rec = match Recording::<f32, _, audio::RecordingError>::controlled(
+                        session,
+                        collector,
+                        monitor.as_ref().map(Monitor::tap),
                     ) {
                         Ok(new_rec) => new_rec,
                         Err(e) => {
-                            responses.send(Response::Error(e.to_string()))?;
+                            self.state.abort();
+                            reply(Response::Error(e.to_string()))?;
                             continue;
                         }
                     };
 
+                    if let Err(e) = new_rec.start() {
+                        log::error!("Failed to start recording: {e}");
+                        // Releases the device if it did open; its error is the same failure
+                        let _ = new_rec.stop();
+                        self.state.abort();
+                        reply(Response::Error(e.to_string()))?;
+                        continue;
+                    }
                     rec = Some(new_rec);
+                    self.dictation = dictation;
+                    listening.pause();
 
-                    rec.as_mut().unwrap().start();
                     let now = SystemTime::now()
                         .duration_since(SystemTime::UNIX_EPOCH)
                         .unwrap()
                         .as_millis();
-                    responses.send(Response::Ack(now))?;
+                    self.events.send(&Event::Started {
+                        recording: generation,
+                        at: now,
+                        wake: matches!(command, Command::Wake(_)),
+                    });
+                    reply(Response::Ack(now))?;
                     log::debug!("Successfully sent ACK");
                 }
 
-                Command::Stop => {
+                Command::Stop | Command::AutoStop(..) => {
                     assert!(rec.is_some());
                     assert!(!new_state.running());
 
-                    let (metadata, audio) = rec.take().unwrap().stop()?;
-                    let job = Job::builder()
-                        .model(
-                            new_state
-                                .session()
-                                .and_then(Session::model)
-                                .unwrap_or_default(),
-                        )
-                        .strategy(self.config.strategy())
-                        .audio(audio)
-                        .prompt(new_state.prompt())
-                        .sample_rate(metadata.sample_rate.0)
-                        .build()
-                        .map_err(whisper::Error::from)?;
-
-                    to_whisper.send(job)?;
-                    let now = std::time::Instant::now();
-
-                    let transcription = whisper_output
-                        .iter()
-                        .next()
-                        .ok_or(Error::NoTranscriptionResult)?;
-
-                    match transcription {
-                        Ok(t) => {
-                            let t = Transcription(t).process();
-
-                            if t.is_some() {
-                                log::info!("Transcribed: \"{}\"", t.as_ref().unwrap().content());
-                                log::info!("Took {:?} to transcribe", now.elapsed(),);
-                            } else {
-                                log::info!("No transcription");
-                            }
+                    let reason = match command {
+                        Command::AutoStop(_, reason) => *reason,
+                        _ => StopReason::Requested,
+                    };
+                    listening.resume();
+                    let response = self.finish_recording(
+                        rec.take().unwrap(),
+                        new_state,
+                        reason,
+                        &worker,
+                        &mut exit_code,
+                    )?;
+
+                    if reason == StopReason::Requested {
+                        responses.send(response)?;
+                    } else {
+                        // Held for the stop command that would have ended it
+                        pending = Some(response);
+                    }
+                    self.events.send(&Event::Stopped {
+                        recording: generation,
+                        reason,
+                    });
+                }
+                Command::Cancel => {
+                    assert!(!new_state.running());
+                    listening.resume();
+
+                    match rec.take().unwrap().stop() {
+                        Ok((metadata, collected)) => log::info!(
+                            "Cancelled recording from {}, discarding {} samples",
+                            metadata.device_name(),
+                            collected.captured.audio().len()
+                        ),
+                        Err(e) => log::warn!("Cancelled recording failed to stop cleanly: {e}"),
+                    }
+                    if let Some(dictation) = self.dictation.take() {
+                        dictation.cancel();
+                    }
+                    self.events.send(&Event::Stopped {
+                        recording: generation,
+                        reason: StopReason::Cancelled,
+                    });
+                    responses.send(Response::ack())?;
+                }
+                Command::Pause => {
+                    assert!(new_state.paused());
+                    gate.pause();
+                    log::info!("Recording paused");
+                    responses.send(Response::ack())?;
+                }
+                Command::Resume => {
+                    assert!(!new_state.paused());
+                    gate.resume();
+                    log::info!("Recording resumed");
+                    responses.send(Response::ack())?;
+                }
+                Command::Recall(secs) => {
+                    let Some(audio) = monitor.as_ref().and_then(|m| m.recall(*secs)) else {
+                        responses.send(Response::Error(
+                            "rolling capture is disabled, start the daemon with --rolling-secs"
+                                .to_string(),
+                        ))?;
+                        continue;
+                    };
 
-                            responses.send(Response::Transcription {
-                                content: t.map(|t| t.content().to_string()),
-                                mode: new_state.mode(),
-                            })?;
-                        }
-                        Err(e) => {
-                            log::error!("{e}");
-                            responses.send(Response::Transcription {
-                                content: None,
-                                mode: new_state.mode(),
-                            })?;
-                            exit_code = 1;
+                    let content = if let Some(audio) = self.config.trim(audio, new_state.session())
+                    {
+                        let job = Job::builder()
+                            .model(
+                                new_state
+                                    .session()
+                                    .and_then(Session::model)
+                                    .unwrap_or_default(),
+                            )
+                            .strategy(self.config.strategy())
+                            .audio(audio)
+                            .prompt(None)
+                            .sample_rate(OUTPUT_SAMPLE_RATE)
+                            .build()
+                            .map_err(whisper::Error::from)?;
+
+                        match worker.transcribe(job)? {
+                            Ok(t) => Transcription(t).process().map(|t| t.content().to_string()),
+                            Err(e) => {
+                                log::error!("{e}");
+                                None
+                            }
                         }
-                    }
+                    } else {
+                        log::info!("No speech to recall");
+                        None
+                    };
+                    log::info!("Recalled {secs}s: {content:?}");
+
+                    responses.send(Response::Transcription {
+                        content,
+                        mode: new_state.mode(),
+                        fault: None,
+                        stop_reason: None,
+                        levels: None,
+                        recording: None,
+                    })?;
+                }
+                Command::Devices(ref host) => {
+                    let host = host.as_deref().or(self.config.audio_host.as_deref());
+                    responses.send(match audio::list_devices(host) {
+                        Ok(devices) => Response::Devices(devices),
+                        Err(e) => Response::Error(e.to_string()),
+                    })?;
+                }
+                Command::Hosts => {
+                    responses.send(Response::Hosts(audio::list_hosts()))?;
                 }
                 Command::Reset => {
                     log::info!("Resetting");
@@ -205,6 +505,10 @@ impl Daemon {
         responses.send(Response::Exit(exit_code))?;
         // Done responding
         drop(responses);
+        // The transcription worker finishes once nothing can queue jobs for it
+        self.dictation = None;
+        drop(wake_check);
+        drop(worker);
 
         if let Err(e) = tx_worker.join() {
             log::error!(
@@ -222,6 +526,472 @@ impl Daemon {
         }
         Ok(false)
     }
+
+    /// Stops `rec` and transcribes what it captured, returning the response for whoever
+    /// stopped it. A continuous recording's dictation is finished with the audio after its
+    /// last utterance.
+    fn finish_recording(
+        &mut self,
+        rec: Recording<f32, Collected>,
+        state: &state::State,
+        stop_reason: StopReason,
+        worker: &Worker,
+        exit_code: &mut u8,
+    ) -> Result<Response, Error> {
+        let dictation = self.dictation.take();
+        let (
+            metadata,
+            Collected {
+                captured,
+                samples,
+                levels,
+                remainder,
+            },
+        ) = match rec.stop() {
+            Ok(stopped) => stopped,
+            Err(e) => {
+                log::error!("Recording failed: {e}");
+                return Ok(Response::Error(e.to_string()));
+            }
+        };
+        log::info!("Recording ended: {stop_reason}");
+        if levels.is_silent() {
+            log::warn!("Recording was silent throughout, is the right input selected?");
+        } else if levels.clipped_windows() > 0 {
+            log::warn!("Input clipped in {} windows", levels.clipped_windows());
+        }
+        let (audio, errors) = captured.into_parts();
+        let report = metadata.report(samples);
+        log::info!("Recorded {report}");
+        let fault = Fault::from_errors(&errors);
+        if let Some(ref fault) = fault {
+            log::warn!("Recording interrupted: {fault:?}");
+        }
+        let archived = match self.config.archive() {
+            Some(_) if dictation.is_some() => {
+                log::info!("Continuous recordings aren't archived");
+                None
+            }
+            Some(archive) => archive
+                .write_audio(&audio)
+                .inspect_err(|e| log::error!("Failed to archive recording: {e}"))
+                .ok(),
+            None => None,
+        };
+
+        let content = if let Some(dictation) = dictation {
+            let dictated = dictation.finish(remainder.unwrap_or_default());
+            (!dictated.is_empty()).then(|| dictated.join(" "))
+        } else if let Some(audio) = self.config.trim(audio, state.session()) {
+            let job = Job::builder()
+                .model(state.session().and_then(Session::model).unwrap_or_default())
+                .strategy(self.config.strategy())
+                .audio(audio)
+                .prompt(state.prompt())
+                .sample_rate(metadata.output_rate())
+                .build()
+                .map_err(whisper::Error::from)?;
+
+            let now = std::time::Instant::now();
+            let transcription = worker.transcribe(job)?;
+
+            match transcription {
+                Ok(t) => {
+                    let t = Transcription(t).process();
+
+                    if t.is_some() {
+                        log::info!("Transcribed: \"{}\"", t.as_ref().unwrap().content());
+                        log::info!("Took {:?} to transcribe", now.elapsed(),);
+                    } else {
+                        log::info!("No transcription");
+                    }
+
+                    t.map(|t| t.content().to_string())
+                }
+                Err(e) => {
+                    log::error!("{e}");
+                    *exit_code = 1;
+                    None
+                }
+            }
+        } else {
+            log::info!("No speech detected, skipping transcription");
+            None
+        };
+
+        if let Some(ref entry) = archived {
+            self.config
+                .write_sidecar(entry, state, &metadata, content.as_deref());
+        }
+
+        Ok(Response::Transcription {
+            content,
+            mode: state.mode(),
+            fault,
+            stop_reason: Some(stop_reason),
+            levels: Some(levels),
+            recording: Some(report),
+        })
+    }
+
+    /// Starts transcribing the utterances of a continuous recording of `session`.
+    fn start_dictation(&self, session: &Session, worker: &Worker) -> Dictation {
+        let model = session.model().unwrap_or_default();
+        let strategy = self.config.strategy();
+        let prompt = session.prompt().map(str::to_owned);
+        let job = move |audio| {
+            Job::builder()
+                .model(model)
+                .strategy(strategy.clone())
+                .audio(audio)
+                .prompt(prompt.clone())
+                .sample_rate(OUTPUT_SAMPLE_RATE)
+                .build()
+                .map_err(|e| Error::from(whisper::Error::from(e)))
+        };
+        Dictation::start(
+            worker.clone(),
+            self.config.trimming(Some(session)),
+            job,
+            self.events.clone(),
+        )
+    }
+
+    /// Starts checking clips of speech for `phrase`, each with the wake model.
+    fn start_wake_check(
+        &self,
+        phrase: WakePhrase,
+        worker: &Worker,
+        to_daemon: Sender<Command>,
+    ) -> WakeCheck {
+        let model = self.config.wake_model.unwrap_or(Model::Base);
+        let strategy = self.config.strategy();
+        let job = move |audio| {
+            Job::builder()
+                .model(model)
+                .strategy(strategy.clone())
+                .audio(audio)
+                .prompt(None)
+                .sample_rate(OUTPUT_SAMPLE_RATE)
+                .build()
+                .map_err(|e| Error::from(whisper::Error::from(e)))
+        };
+        WakeCheck::start(
+            phrase,
+            worker.clone(),
+            job,
+            self.config.wake_session(),
+            to_daemon,
+            self.events.clone(),
+        )
+    }
+}
+
+/// How to cut the silence around speech before it's transcribed.
+#[derive(Debug, Clone, Copy)]
+struct Trim {
+    vad: vad::Config,
+    /// Silence kept either side of the speech, `None` when trimming is turned off
+    margin: Option<Duration>,
+}
+
+impl Trim {
+    /// Trims the silence around the speech in `audio`, or returns `None` when there's no
+    /// speech worth sending to whisper.
+    fn apply(&self, mut audio: Vec<f32>) -> Option<Vec<f32>> {
+        let Some(margin) = self.margin else {
+            return Some(audio);
+        };
+
+        let range = self.vad.speech_range(&audio, margin)?;
+        log::debug!("Trimmed {} samples to {range:?}", audio.len());
+        audio.truncate(range.end);
+        audio.drain(..range.start);
+        Some(audio)
+    }
+}
+
+/// Transcribes a continuous recording's utterances as they're cut, on a thread of its own so
+/// the daemon carries on handling commands meanwhile. Dropping it throws away the ones not
+/// transcribed yet.
+struct Dictation {
+    utterances: Sender<Vec<f32>>,
+    /// Dropped to stop early
+    cancel: Sender<()>,
+    handle: JoinHandle<Vec<String>>,
+}
+
+impl Dictation {
+    /// Trims each utterance with `trim`, has `worker` transcribe it as `job` describes and
+    /// reports the transcript to `events`.
+    fn start(
+        worker: Worker,
+        trim: Trim,
+        job: impl Fn(Vec<f32>) -> Result<Job, Error> + Send + 'static,
+        events: Broadcast<Event>,
+    ) -> Self {
+        let (utterances, queue) = unbounded::<Vec<f32>>();
+        let (cancel, cancelled) = bounded::<()>(0);
+        let handle = std::thread::spawn(move || {
+            let mut dictated = Vec::new();
+            loop {
+                let audio = select! {
+                    recv(queue) -> audio => match audio {
+                        Ok(audio) => audio,
+                        Err(_) => break,
+                    },
+                    recv(cancelled) -> _ => break,
+                };
+                let Some(audio) = trim.apply(audio) else {
+                    log::debug!("No speech in utterance, skipping it");
+                    continue;
+                };
+
+                let content = worker.transcript(job(audio));
+                if matches!(cancelled.try_recv(), Err(TryRecvError::Disconnected)) {
+                    break;
+                }
+                if let Some(content) = content {
+                    log::info!("Transcribed utterance: \"{content}\"");
+                    events.send(&Event::Transcription(content.clone()));
+                    dictated.push(content);
+                }
+            }
+            dictated
+        });
+
+        Self {
+            utterances,
+            cancel,
+            handle,
+        }
+    }
+
+    /// Where the recording's collector sends each utterance.
+    fn sender(&self) -> Sender<Vec<f32>> {
+        self.utterances.clone()
+    }
+
+    /// Transcribes `remainder` after any utterances still queued, once the collector has
+    /// stopped, returning every transcript in order.
+    fn finish(self, remainder: Vec<f32>) -> Vec<String> {
+        let Self {
+            utterances,
+            cancel,
+            handle,
+        } = self;
+        let _ = utterances.send(remainder);
+        drop(utterances);
+        let dictated = handle.join().unwrap_or_else(|_| {
+            log::error!("Dictation thread panicked");
+            Vec::new()
+        });
+        drop(cancel);
+        dictated
+    }
+
+    /// Throws away the utterances not transcribed yet.
+    fn cancel(self) {
+        log::info!("Discarding {} utterances", self.utterances.len());
+    }
+}
+
+/// Hands jobs to the transcription worker one at a time, each with its own reply, so the
+/// daemon and its dictation can share it.
+#[derive(Clone)]
+struct Worker(Sender<(Job, Sender<TranscribeResult>)>);
+
+impl Worker {
+    /// Relays to the worker that takes `jobs` and answers on `results`, until every clone is
+    /// dropped.
+    fn spawn(jobs: Sender<Job>, results: Receiver<TranscribeResult>) -> Self {
+        let (send, requests) = unbounded::<(Job, Sender<TranscribeResult>)>();
+        std::thread::spawn(move || {
+            for (job, reply) in requests {
+                if jobs.send(job).is_err() {
+                    break;
+                }
+                let Ok(result) = results.recv() else {
+                    break;
+                };
+                let _ = reply.send(result);
+            }
+        });
+        Self(send)
+    }
+
+    /// Queues `job` and waits for its result.
+    fn transcribe(&self, job: Job) -> Result<TranscribeResult, Error> {
+        let (reply, result) = bounded(1);
+        self.0.send((job, reply))?;
+        result.recv().or(Err(Error::NoTranscriptionResult))
+    }
+
+    /// Transcribes `job` to plain text, logging whatever goes wrong.
+    fn transcript(&self, job: Result<Job, Error>) -> Option<String> {
+        match job.and_then(|job| self.transcribe(job)) {
+            Ok(Ok(t)) => Transcription(t).process().map(|t| t.content().to_string()),
+            Ok(Err(e)) => {
+                log::error!("{e}");
+                None
+            }
+            Err(e) => {
+                log::error!("Couldn't transcribe: {e}");
+                None
+            }
+        }
+    }
+}
+
+/// Checks short clips of speech heard between recordings for the wake phrase on a thread of
+/// its own, and has the daemon start a recording when one says it. Dropping it stops the
+/// checks.
+struct WakeCheck {
+    clips: Sender<Vec<f32>>,
+    /// Dropped to stop
+    _cancel: Sender<()>,
+}
+
+impl WakeCheck {
+    /// Transcribes each clip as `job` describes, starting `session` when it has `phrase` in it.
+    fn start(
+        phrase: WakePhrase,
+        worker: Worker,
+        job: impl Fn(Vec<f32>) -> Result<Job, Error> + Send + 'static,
+        session: Session,
+        to_daemon: Sender<Command>,
+        events: Broadcast<Event>,
+    ) -> Self {
+        // Room for one clip to wait while another is checked, so they can't pile up
+        let (clips, queue) = bounded::<Vec<f32>>(1);
+        let (cancel, cancelled) = bounded::<()>(0);
+        std::thread::spawn(move || loop {
+            let clip = select! {
+                recv(queue) -> clip => match clip {
+                    Ok(clip) => clip,
+                    Err(_) => break,
+                },
+                recv(cancelled) -> _ => break,
+            };
+            let heard = worker.transcript(job(clip));
+            log::debug!("Heard {heard:?} while listening");
+            if let Some(heard) = heard.filter(|heard| phrase.matches(heard)) {
+                log::info!("Heard the wake phrase in \"{heard}\"");
+                events.send(&Event::Wake(heard));
+                if to_daemon.send(Command::Wake(session.clone())).is_err() {
+                    break;
+                }
+            }
+        });
+
+        Self {
+            clips,
+            _cancel: cancel,
+        }
+    }
+
+    /// Where the listener sends each clip. Clips that arrive while the checks are behind
+    /// are dropped.
+    fn sender(&self) -> Sender<Vec<f32>> {
+        self.clips.clone()
+    }
+}
+
+/// What a recording's collector hands back once it stops.
+struct Collected {
+    /// Only the errors for a continuous recording, whose audio goes to its dictation instead
+    captured: Captured<f32>,
+    samples: usize,
+    levels: LevelSummary,
+    /// For a continuous recording, the audio after its last complete utterance
+    remainder: Option<Vec<f32>>,
+}
+
+/// Builds the sink for recording number `id`: keeps the audio `gate` admits, reports its
+/// levels to `events`, tells the daemon when the session's limits are reached and, for a
+/// continuous session, sends each utterance to `dictation`.
+fn collector(
+    id: u64,
+    session: &Session,
+    vad: vad::Config,
+    gate: Gate,
+    to_daemon: Sender<Command>,
+    dictation: Option<Sender<Vec<f32>>>,
+    events: Broadcast<Event>,
+) -> sync::ProcessNode<AudioMessage<f32>, Collected> {
+    let (max_duration, idle_timeout) = (session.max_duration(), session.idle_timeout());
+    let endpointing = session.endpointing();
+    let continuous = session.continuous().zip(dictation);
+    sync::ProcessNode::new(move |it| {
+        let mut watch = AutoStop::new(max_duration, idle_timeout, vad);
+        if let Some(endpointing) = endpointing {
+            watch = watch.with_endpointing(endpointing);
+        }
+        let mut dictation = continuous
+            .clone()
+            .map(|(timing, dictation)| (Segmenter::new(timing, &vad), dictation));
+        let mut meter = LevelMeter::new();
+        let mut samples = 0;
+        let admitted = it.filter(|msg| gate.admits(msg)).inspect(|msg| {
+            let AudioMessage::Data(block) = msg else {
+                return;
+            };
+            samples += block.len();
+            for level in meter.push(block) {
+                events.send(&Event::Level(level));
+            }
+            if let Some((segmenter, to_dictation)) = dictation.as_mut() {
+                for utterance in segmenter.push(block) {
+                    let _ = to_dictation.send(utterance);
+                }
+            }
+            if let Some(reason) = watch.push(block) {
+                log::info!("Stopping recording: {reason}");
+                let _ = to_daemon.send(Command::AutoStop(id, reason));
+            }
+        });
+        // A continuous recording's audio has been cut up and handed on already
+        let captured = if continuous.is_some() {
+            Captured::collect(admitted.filter(|msg| matches!(msg, AudioMessage::Error(_))))
+        } else {
+            Captured::collect(admitted)
+        };
+        Collected {
+            captured,
+            samples,
+            levels: meter.summary(),
+            remainder: dictation.map(|(segmenter, _)| segmenter.into_remainder()),
+        }
+    })
+}
+
+/// Longest speech that's checked for the wake phrase.
+const WAKE_CLIP: Duration = Duration::from_secs(3);
+
+/// Builds the sink for audio heard while listening for the wake phrase, which cuts out each
+/// short clip of speech `gate` admits and sends it to `clips` to be checked.
+fn listener(
+    vad: vad::Config,
+    gate: Gate,
+    clips: Sender<Vec<f32>>,
+) -> sync::ProcessNode<AudioMessage<f32>, ()> {
+    sync::ProcessNode::new(move |it| {
+        let mut speech = SpeechClips::new(&vad, WAKE_CLIP);
+        for msg in it.filter(|msg| gate.admits(msg)) {
+            let AudioMessage::Data(block) = msg else {
+                continue;
+            };
+            for clip in speech.push(&block) {
+                match clips.try_send(clip) {
+                    Ok(()) => (),
+                    Err(TrySendError::Full(_)) => {
+                        log::debug!("Still checking for the wake phrase, dropping a clip");
+                    }
+                    Err(TrySendError::Disconnected(_)) => return,
+                }
+            }
+        }
+    })
 }
 
 #[derive(Debug, Clone)]
@@ -1,7 +1,33 @@
 use super::state::Mode;
+use crate::audio::{DeviceInfo, HostInfo, LevelSummary, ProcessError, RecordingInfo, StopReason};
 
+/// Something that went wrong during a recording without losing what was captured before it.
 #[derive(Debug, Clone, Eq, PartialEq, serde::Serialize, serde::Deserialize)]
 #[serde(tag = "type", content = "data")]
+pub enum Fault {
+    /// The input device went away partway through
+    #[serde(rename = "device_lost")]
+    DeviceLost(String),
+
+    #[serde(rename = "stream_error")]
+    StreamError(String),
+}
+
+impl Fault {
+    /// Summarizes the errors reported during a recording, preferring a lost device since that
+    /// explains everything after it.
+    #[must_use]
+    pub fn from_errors(errors: &[ProcessError]) -> Option<Self> {
+        errors
+            .iter()
+            .find(|e| e.is_device_lost())
+            .map(|e| Self::DeviceLost(e.to_string()))
+            .or_else(|| errors.first().map(|e| Self::StreamError(e.to_string())))
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, serde::Serialize, serde::Deserialize)]
+#[serde(tag = "type", content = "data")]
 pub enum Response {
     #[serde(rename = "ack")]
     Ack(u128),
@@ -19,7 +45,27 @@ pub enum Response {
     NewMode(Mode),
 
     #[serde(rename = "transcription")]
-    Transcription { content: Option<String>, mode: Mode },
+    Transcription {
+        content: Option<String>,
+        mode: Mode,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        fault: Option<Fault>,
+        /// Set for recordings, not for recalls
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        stop_reason: Option<StopReason>,
+        /// Input levels over the recording
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        levels: Option<LevelSummary>,
+        /// Where the recording came from, how long it was, and when it was made
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        recording: Option<RecordingInfo>,
+    },
+
+    #[serde(rename = "devices")]
+    Devices(Vec<DeviceInfo>),
+
+    #[serde(rename = "hosts")]
+    Hosts(Vec<HostInfo>),
 }
 
 impl Response {
@@ -41,6 +87,10 @@ impl From<sttx::Timing> for Response {
         Self::Transcription {
             content: Some(t.content().to_string()),
             mode: Mode::default(),
+            fault: None,
+            stop_reason: None,
+            levels: None,
+            recording: None,
         }
     }
 }
@@ -56,11 +106,26 @@ impl std::fmt::Display for Response {
             Self::Transcription {
                 content: Some(s),
                 mode,
+                ..
             } => match mode {
                 Mode::Standard => write!(f, "TX {s}"),
                 Mode::LiveTyping => write!(f, "TX_LIVE {s}"),
             },
             Self::Transcription { content: None, .. } => write!(f, "TX_EMPTY"),
+            Self::Devices(devices) => {
+                write!(f, "DEVICES")?;
+                for device in devices {
+                    write!(f, "\n{device}")?;
+                }
+                Ok(())
+            }
+            Self::Hosts(hosts) => {
+                write!(f, "HOSTS")?;
+                for host in hosts {
+                    write!(f, "\n{host}")?;
+                }
+                Ok(())
+            }
         }
     }
 }
@@ -115,6 +180,10 @@ mod tests {
         let response = Response::Transcription {
             content: Some("hello world".to_string()),
             mode: Mode::Standard,
+            fault: None,
+            stop_reason: None,
+            levels: None,
+            recording: None,
         };
         let expected = r#"{"type":"transcription","data":{"content":"hello world","mode":{"type":"standard"}}}"#;
         let serialized = serde_json::to_string(&response).unwrap();
@@ -126,10 +195,48 @@ mod tests {
         let response = Response::Transcription {
             content: None,
             mode: Mode::LiveTyping,
+            fault: None,
+            stop_reason: None,
+            levels: None,
+            recording: None,
         };
         let expected =
             r#"{"type":"transcription","data":{"content":null,"mode":{"type":"live_typing"}}}"#;
         let serialized = serde_json::to_string(&response).unwrap();
         assert_eq!(serialized, expected);
     }
+
+    #[test]
+    fn test_serialize_transcription_with_fault() {
+        let response = Response::Transcription {
+            content: Some("hello".to_string()),
+            mode: Mode::Standard,
+            fault: Some(Fault::DeviceLost("gone".to_string())),
+            stop_reason: None,
+            levels: None,
+            recording: None,
+        };
+        let expected = r#"{"type":"transcription","data":{"content":"hello","mode":{"type":"standard"},"fault":{"type":"device_lost","data":"gone"}}}"#;
+        let serialized = serde_json::to_string(&response).unwrap();
+        assert_eq!(serialized, expected);
+    }
+
+    #[test]
+    fn test_serialize_transcription_with_stop_reason() {
+        let response = Response::Transcription {
+            content: Some("hello".to_string()),
+            mode: Mode::Standard,
+            fault: None,
+            stop_reason: Some(StopReason::Idle),
+            levels: None,
+            recording: None,
+        };
+        let expected = r#"{"type":"transcription","data":{"content":"hello","mode":{"type":"standard"},"stop_reason":{"type":"idle"}}}"#;
+        let serialized = serde_json::to_string(&response).unwrap();
+        assert_eq!(serialized, expected);
+        assert_eq!(
+            serde_json::from_str::<Response>(expected).unwrap(),
+            response
+        );
+    }
 }
@@ -8,6 +8,8 @@ pub enum Audio {
     #[default]
     Idle,
     Started(Session),
+    /// Started, but its audio is being dropped until it's resumed
+    Paused(Session),
     Stopped(Session),
 }
 
@@ -18,9 +20,15 @@ pub struct State {
 }
 
 impl State {
+    /// Whether a recording is open, even if it's paused.
     #[must_use]
     pub fn running(&self) -> bool {
-        matches!(self.audio, Audio::Started(_))
+        matches!(self.audio, Audio::Started(_) | Audio::Paused(_))
+    }
+
+    #[must_use]
+    pub fn paused(&self) -> bool {
+        matches!(self.audio, Audio::Paused(_))
     }
 
     #[must_use]
@@ -31,14 +39,16 @@ impl State {
     #[must_use]
     pub fn session(&self) -> Option<&Session> {
         match &self.audio {
-            Audio::Started(s) | Audio::Stopped(s) => Some(s),
+            Audio::Started(s) | Audio::Paused(s) | Audio::Stopped(s) => Some(s),
             Audio::Idle => None,
         }
     }
 
     pub fn prompt(&self) -> Option<String> {
         match &self.audio {
-            Audio::Started(s) | Audio::Stopped(s) => s.prompt().map(str::to_owned),
+            Audio::Started(s) | Audio::Paused(s) | Audio::Stopped(s) => {
+                s.prompt().map(str::to_owned)
+            }
             Audio::Idle => None,
         }
     }
@@ -49,13 +59,13 @@ impl State {
                 self.audio = Audio::Started(session);
                 true
             }
-            Audio::Started(_) => false,
+            Audio::Started(_) | Audio::Paused(_) => false,
         }
     }
 
     fn stop(&mut self) -> bool {
         match &self.audio {
-            Audio::Started(s) => {
+            Audio::Started(s) | Audio::Paused(s) => {
                 self.audio = Audio::Stopped(s.clone());
                 true
             }
@@ -63,6 +73,41 @@ impl State {
         }
     }
 
+    fn cancel(&mut self) -> bool {
+        if self.running() {
+            self.audio = Audio::Idle;
+            true
+        } else {
+            false
+        }
+    }
+
+    fn pause(&mut self) -> bool {
+        match &self.audio {
+            Audio::Started(s) => {
+                self.audio = Audio::Paused(s.clone());
+                true
+            }
+            _ => false,
+        }
+    }
+
+    fn resume(&mut self) -> bool {
+        match &self.audio {
+            Audio::Paused(s) => {
+                self.audio = Audio::Started(s.clone());
+                true
+            }
+            _ => false,
+        }
+    }
+
+    /// Marks a started session as stopped when its recording never got going, e.g. because
+    /// the device couldn't be opened, so the next start isn't refused.
+    pub fn abort(&mut self) -> bool {
+        self.stop()
+    }
+
     pub fn change_mode(&mut self, mode: Mode) -> bool {
         if self.mode == mode {
             false
@@ -74,8 +119,11 @@ impl State {
 
     pub fn next_state(&mut self, cmd: &Command) -> bool {
         match cmd {
-            Command::Start(session) => self.start(session.clone()),
-            Command::Stop => self.stop(),
+            Command::Start(session) | Command::Wake(session) => self.start(session.clone()),
+            Command::Stop | Command::AutoStop(..) => self.stop(),
+            Command::Cancel => self.cancel(),
+            Command::Pause => self.pause(),
+            Command::Resume => self.resume(),
             Command::Mode(mode) if !self.running() => self.change_mode(mode.clone()),
             Command::Mode(_) => false,
             // Nothing changes about the state when we send these commands, but we still need to
@@ -83,7 +131,11 @@ impl State {
             //
             // TODO: I should consider making the event loop not sort of dependent on changes in
             // the state and find some other way to represent that.
-            Command::Reset | Command::Respond(_) => true,
+            Command::Reset
+            | Command::Respond(_)
+            | Command::Recall(_)
+            | Command::Devices(_)
+            | Command::Hosts => true,
         }
     }
 }
@@ -111,7 +163,7 @@ impl std::fmt::Display for Mode {
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::whisper::transcription::Model;
+    use crate::{audio::StopReason, whisper::transcription::Model};
 
     fn create_dummy_session() -> Session {
         Session::new(
@@ -197,6 +249,16 @@ mod tests {
         assert_eq!(state_stopped.audio, Audio::Stopped(session));
     }
 
+    #[test]
+    fn test_abort_allows_restart() {
+        let mut state = State::default();
+        let session = create_dummy_session();
+        assert!(state.next_state(&Command::Start(session.clone())));
+        assert!(state.abort());
+        assert_eq!(state.audio, Audio::Stopped(session.clone()));
+        assert!(state.next_state(&Command::Start(session)));
+    }
+
     #[test]
     fn test_running() {
         let mut state = State::default();
@@ -302,6 +364,77 @@ mod tests {
         assert_eq!(state.audio, Audio::Stopped(session));
     }
 
+    #[test]
+    fn test_next_state_auto_stop() {
+        let mut state = State::default();
+        let command = Command::AutoStop(1, StopReason::Idle);
+        assert!(!state.next_state(&command));
+
+        let session = create_dummy_session();
+        state.audio = Audio::Started(session.clone());
+        assert!(state.next_state(&command));
+        assert_eq!(state.audio, Audio::Stopped(session));
+    }
+
+    #[test]
+    fn test_wake_only_while_idle() {
+        let mut state = State::default();
+        let session = create_dummy_session();
+        assert!(state.next_state(&Command::Wake(session.clone())));
+        assert_eq!(state.audio, Audio::Started(session.clone()));
+        assert!(!state.next_state(&Command::Wake(session)));
+    }
+
+    #[test]
+    fn test_pause_and_resume() {
+        let mut state = State::default();
+        assert!(!state.next_state(&Command::Pause));
+        assert!(!state.next_state(&Command::Resume));
+
+        let session = create_dummy_session();
+        state.audio = Audio::Started(session.clone());
+        assert!(!state.next_state(&Command::Resume));
+        assert!(state.next_state(&Command::Pause));
+        assert_eq!(state.audio, Audio::Paused(session.clone()));
+        assert!(state.running());
+        assert!(state.paused());
+        assert!(!state.next_state(&Command::Pause));
+        assert!(!state.next_state(&Command::Start(session.clone())));
+        assert!(!state.next_state(&Command::Mode(Mode::Standard)));
+
+        assert!(state.next_state(&Command::Resume));
+        assert_eq!(state.audio, Audio::Started(session.clone()));
+        assert!(!state.paused());
+    }
+
+    #[test]
+    fn test_stop_while_paused() {
+        let mut state = State::default();
+        let session = create_dummy_session();
+        state.audio = Audio::Paused(session.clone());
+        assert!(state.next_state(&Command::Stop));
+        assert_eq!(state.audio, Audio::Stopped(session));
+        assert!(!state.running());
+    }
+
+    #[test]
+    fn test_cancel_returns_to_idle() {
+        let mut state = State::default();
+        assert!(!state.next_state(&Command::Cancel));
+
+        let session = create_dummy_session();
+        state.audio = Audio::Started(session.clone());
+        assert!(state.next_state(&Command::Cancel));
+        assert_eq!(state.audio, Audio::Idle);
+
+        state.audio = Audio::Paused(session.clone());
+        assert!(state.next_state(&Command::Cancel));
+        assert_eq!(state.audio, Audio::Idle);
+
+        state.audio = Audio::Stopped(session);
+        assert!(!state.next_state(&Command::Cancel));
+    }
+
     #[test]
     fn test_next_state_mode() {
         let mut state = State::default(); // LiveTyping, not running
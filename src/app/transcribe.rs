@@ -0,0 +1,86 @@
+use std::{
+    fs::File,
+    io::{self, Read},
+    path::PathBuf,
+};
+
+use crossbeam::channel::unbounded;
+
+use super::{Error, Transcription};
+use crate::{
+    audio::{
+        dsp::DspConfig,
+        pcm::{self, PcmSpec},
+        OUTPUT_SAMPLE_RATE,
+    },
+    whisper::{
+        self,
+        transcription::{Job, Model},
+    },
+};
+
+/// Transcribes a single raw PCM stream without going through the daemon's command loop.
+#[derive(Debug, clap::Args)]
+pub struct TranscribeInit {
+    #[clap(short, long, value_parser = whisper::transcription::parse_strategy)]
+    strategy: Option<whisper::transcription::StrategyOpt>,
+
+    #[clap(long)]
+    model_dir: PathBuf,
+
+    #[clap(short, long, value_enum)]
+    model: Option<Model>,
+
+    #[clap(long)]
+    prompt: Option<String>,
+
+    #[clap(flatten)]
+    pcm: PcmSpec,
+
+    #[clap(flatten)]
+    dsp: DspConfig,
+
+    /// File or named pipe to read from (defaults to stdin)
+    input: Option<PathBuf>,
+}
+
+impl TranscribeInit {
+    /// Reads the input to its end and returns the processed transcription, if any.
+    pub fn run(&self) -> Result<Option<String>, Error> {
+        let reader: Box<dyn Read> = match self.input {
+            Some(ref path) => Box::new(File::open(path)?),
+            None => Box::new(io::stdin().lock()),
+        };
+
+        let audio = pcm::resample(reader, &self.pcm, &self.dsp)?;
+
+        let job = Job::builder()
+            .model(self.model.unwrap_or_default())
+            .strategy(self.strategy.clone().unwrap_or_default().into())
+            .audio(audio)
+            .prompt(self.prompt.clone())
+            .sample_rate(OUTPUT_SAMPLE_RATE)
+            .build()
+            .map_err(whisper::Error::from)?;
+
+        let (to_whisper, jobs) = unbounded();
+        let (results, tx_worker) = whisper::transcription_worker(self.model_dir.as_path(), jobs)?;
+
+        to_whisper.send(job)?;
+        drop(to_whisper);
+
+        let transcription = results
+            .iter()
+            .next()
+            .ok_or(Error::NoTranscriptionResult)?
+            .map_err(whisper::Error::from)?;
+
+        if tx_worker.join().is_err() {
+            log::error!("Transcription worker thread panicked");
+        }
+
+        Ok(Transcription(transcription)
+            .process()
+            .map(|t| t.content().to_string()))
+    }
+}
@@ -0,0 +1,43 @@
+/// The phrase that starts a recording hands-free. Transcripts are matched loosely, since
+/// whisper's capitalisation and punctuation of a few words out of context vary.
+#[derive(Debug, Clone)]
+pub struct WakePhrase(Vec<String>);
+
+impl WakePhrase {
+    #[must_use]
+    pub fn new(phrase: &str) -> Self {
+        Self(words(phrase))
+    }
+
+    /// Whether `transcript` says the phrase, ignoring case and punctuation.
+    #[must_use]
+    pub fn matches(&self, transcript: &str) -> bool {
+        !self.0.is_empty()
+            && words(transcript)
+                .windows(self.0.len())
+                .any(|heard| heard == self.0)
+    }
+}
+
+fn words(text: &str) -> Vec<String> {
+    text.split(|c: char| !c.is_alphanumeric())
+        .filter(|w| !w.is_empty())
+        .map(str::to_lowercase)
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_matches_loosely() {
+        let phrase = WakePhrase::new("Hey computer");
+        assert!(phrase.matches("Hey, computer!"));
+        assert!(phrase.matches(" hey computer."));
+        assert!(phrase.matches("Okay. Hey computer"));
+        assert!(!phrase.matches("Hey computers"));
+        assert!(!phrase.matches("computer, hey"));
+        assert!(!WakePhrase::new("...").matches("anything"));
+    }
+}
@@ -0,0 +1,254 @@
+use std::{
+    fs::{self, File, OpenOptions},
+    io::{self, BufWriter},
+    path::{Path, PathBuf},
+    time::SystemTime,
+};
+
+use serde::Serialize;
+
+use crate::{
+    audio::{Metadata, Session, StreamInfo, OUTPUT_SAMPLE_RATE},
+    whisper::transcription::{Model, StrategyOpt},
+};
+
+#[derive(Debug, thiserror::Error)]
+pub enum Error {
+    #[error("IO error: {0}")]
+    Io(#[from] std::io::Error),
+
+    #[error("WAV error: {0}")]
+    Wav(#[from] hound::Error),
+
+    #[error("JSON error: {0}")]
+    Json(#[from] serde_json::Error),
+}
+
+/// Everything needed to understand what was transcribed and how, written next to the audio.
+/// `session` is null when the daemon had lost track of it, but the rest is still written.
+#[derive(Debug, Serialize)]
+pub struct Sidecar<'a> {
+    pub session: Option<&'a Session>,
+    pub device: &'a str,
+    pub stream_config: StreamInfo,
+    pub model: Model,
+    pub strategy: &'a StrategyOpt,
+    pub prompt: Option<&'a str>,
+    pub transcript: Option<&'a str>,
+}
+
+impl<'a> Sidecar<'a> {
+    #[must_use]
+    pub fn new(
+        session: Option<&'a Session>,
+        metadata: &'a Metadata,
+        strategy: &'a StrategyOpt,
+    ) -> Self {
+        Self {
+            session,
+            device: metadata.device_name(),
+            stream_config: metadata.config().into(),
+            model: session.and_then(Session::model).unwrap_or_default(),
+            strategy,
+            prompt: session.and_then(Session::prompt),
+            transcript: None,
+        }
+    }
+}
+
+/// A directory holding one WAV file and one JSON sidecar per recording.
+#[derive(Debug, Clone)]
+pub struct Archive {
+    dir: PathBuf,
+}
+
+/// A recording whose audio has been written but whose sidecar is still pending.
+#[derive(Debug)]
+pub struct Entry {
+    stem: PathBuf,
+}
+
+impl Archive {
+    /// Mono at the output rate, matching what is handed to whisper.
+    const SPEC: hound::WavSpec = hound::WavSpec {
+        channels: 1,
+        sample_rate: OUTPUT_SAMPLE_RATE,
+        bits_per_sample: 32,
+        sample_format: hound::SampleFormat::Float,
+    };
+
+    #[must_use]
+    pub fn new(dir: PathBuf) -> Self {
+        Self { dir }
+    }
+
+    #[must_use]
+    pub fn dir(&self) -> &Path {
+        &self.dir
+    }
+
+    /// Writes the audio under a name derived from the current time, suffixed if another
+    /// recording already has it.
+    ///
+    /// # Panics
+    ///
+    /// when `std::time::SystemTime::now()` is earlier than `std::time::UNIX_EPOCH`
+    pub fn write_audio(&self, audio: &[f32]) -> Result<Entry, Error> {
+        fs::create_dir_all(&self.dir)?;
+
+        let millis = SystemTime::now()
+            .duration_since(SystemTime::UNIX_EPOCH)
+            .unwrap()
+            .as_millis();
+        let (stem, file) = self.create_unique(&millis.to_string())?;
+
+        let mut writer = hound::WavWriter::new(BufWriter::new(file), Self::SPEC)?;
+        for sample in audio {
+            writer.write_sample(*sample)?;
+        }
+        writer.finalize()?;
+
+        Ok(Entry { stem })
+    }
+
+    /// Creates the WAV file for `name`, or for `name-1`, `name-2` and so on when that's taken,
+    /// returning its stem. Never opens an existing file.
+    fn create_unique(&self, name: &str) -> Result<(PathBuf, File), io::Error> {
+        for n in 0_u32.. {
+            let stem = match n {
+                0 => self.dir.join(name),
+                n => self.dir.join(format!("{name}-{n}")),
+            };
+            match OpenOptions::new()
+                .write(true)
+                .create_new(true)
+                .open(stem.with_extension("wav"))
+            {
+                Ok(file) => return Ok((stem, file)),
+                Err(e) if e.kind() == io::ErrorKind::AlreadyExists => {}
+                Err(e) => return Err(e),
+            }
+        }
+        unreachable!("ran out of suffixes for {name}")
+    }
+}
+
+impl Entry {
+    #[must_use]
+    pub fn audio_path(&self) -> PathBuf {
+        self.stem.with_extension("wav")
+    }
+
+    pub fn write_sidecar(&self, sidecar: &Sidecar) -> Result<(), Error> {
+        let json = serde_json::to_string_pretty(sidecar)?;
+        fs::write(self.stem.with_extension("json"), json)?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A fresh directory for `name`, so tests running at the same time don't collide.
+    fn scratch_dir(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("voice-archive-{}-{name}", std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        dir
+    }
+
+    fn metadata() -> Metadata {
+        Metadata::new(
+            "Mic".to_string(),
+            cpal::StreamConfig {
+                channels: 2,
+                sample_rate: cpal::SampleRate(48_000),
+                buffer_size: cpal::BufferSize::Default,
+            },
+        )
+    }
+
+    #[test]
+    fn test_audio_round_trip() {
+        let dir = scratch_dir("audio");
+        let audio = [0.0, 0.25, -0.5, 1.0];
+        let entry = Archive::new(dir.join("nested"))
+            .write_audio(&audio)
+            .unwrap();
+
+        let path = entry.audio_path();
+        assert_eq!(path.parent(), Some(dir.join("nested").as_path()));
+        let stem = path.file_stem().unwrap().to_str().unwrap();
+        assert!(stem.parse::<u128>().is_ok(), "{stem} isn't a timestamp");
+
+        let mut reader = hound::WavReader::open(&path).unwrap();
+        assert_eq!(reader.spec(), Archive::SPEC);
+        let read: Vec<f32> = reader.samples().map(Result::unwrap).collect();
+        assert_eq!(read, audio);
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn test_taken_names_get_a_suffix() {
+        let dir = scratch_dir("unique");
+        fs::create_dir_all(&dir).unwrap();
+        let archive = Archive::new(dir.clone());
+
+        let (first, _) = archive.create_unique("1000").unwrap();
+        let (second, _) = archive.create_unique("1000").unwrap();
+        let (third, _) = archive.create_unique("1000").unwrap();
+        assert_eq!(first, dir.join("1000"));
+        assert_eq!(second, dir.join("1000-1"));
+        assert_eq!(third, dir.join("1000-2"));
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn test_sidecar_shape() {
+        let dir = scratch_dir("sidecar");
+        let entry = Archive::new(dir.clone()).write_audio(&[0.0; 16]).unwrap();
+        let session = Session::new(None, None, Some("Names: Ada".to_string()), None);
+        let metadata = metadata();
+        let strategy = StrategyOpt::default();
+        entry
+            .write_sidecar(&Sidecar {
+                transcript: Some("hello"),
+                ..Sidecar::new(Some(&session), &metadata, &strategy)
+            })
+            .unwrap();
+
+        let json = fs::read_to_string(entry.audio_path().with_extension("json")).unwrap();
+        let sidecar: serde_json::Value = serde_json::from_str(&json).unwrap();
+        let mut keys: Vec<_> = sidecar.as_object().unwrap().keys().collect();
+        keys.sort();
+        assert_eq!(
+            keys,
+            [
+                "device",
+                "model",
+                "prompt",
+                "session",
+                "strategy",
+                "stream_config",
+                "transcript"
+            ]
+        );
+        assert_eq!(sidecar["device"], "Mic");
+        assert_eq!(
+            sidecar["stream_config"],
+            serde_json::json!({"channels": 2, "sample_rate": 48_000, "buffer_size": null})
+        );
+        assert_eq!(sidecar["prompt"], "Names: Ada");
+        assert_eq!(sidecar["transcript"], "hello");
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn test_sidecar_without_session() {
+        let metadata = metadata();
+        let strategy = StrategyOpt::default();
+        let sidecar = serde_json::to_value(Sidecar::new(None, &metadata, &strategy)).unwrap();
+        assert!(sidecar["session"].is_null());
+        assert_eq!(sidecar["device"], "Mic");
+    }
+}
@@ -0,0 +1,220 @@
+use std::{fmt, time::Duration};
+
+use serde::{Deserialize, Serialize};
+
+use super::{
+    endpoint::{Endpointer, Endpointing},
+    samples, vad,
+};
+
+/// Why a recording ended.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(tag = "type", content = "data")]
+pub enum StopReason {
+    /// A stop command arrived
+    #[serde(rename = "requested")]
+    Requested,
+
+    /// The session's maximum duration was reached
+    #[serde(rename = "max_duration")]
+    MaxDuration,
+
+    /// No speech was detected for the session's idle timeout
+    #[serde(rename = "idle")]
+    Idle,
+
+    /// The speaker finished, going by the session's endpointing
+    #[serde(rename = "end_of_speech")]
+    EndOfSpeech,
+
+    /// A cancel command arrived, so nothing was transcribed
+    #[serde(rename = "cancelled")]
+    Cancelled,
+}
+
+impl fmt::Display for StopReason {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::Requested => write!(f, "stop requested"),
+            Self::MaxDuration => write!(f, "maximum duration reached"),
+            Self::Idle => write!(f, "no speech detected"),
+            Self::EndOfSpeech => write!(f, "speaker finished"),
+            Self::Cancelled => write!(f, "cancelled"),
+        }
+    }
+}
+
+impl StopReason {
+    /// Whether the recording ended without being told to.
+    #[must_use]
+    pub fn is_automatic(&self) -> bool {
+        !matches!(self, Self::Requested | Self::Cancelled)
+    }
+}
+
+/// Watches a recording's resampled audio for a reason to stop it without being asked.
+///
+/// Its [`vad::Frames`] tie it to a thread, so it has to be created on the one that collects
+/// the audio.
+pub struct AutoStop {
+    vad: vad::Config,
+    max_samples: Option<usize>,
+    idle_frames: Option<usize>,
+    endpointer: Option<Endpointer>,
+    frames: Option<vad::Frames>,
+    samples: usize,
+    silent_frames: usize,
+    fired: bool,
+}
+
+impl AutoStop {
+    /// Judges silence for the idle timeout and endpointing with `vad`.
+    #[must_use]
+    pub fn new(
+        max_duration: Option<Duration>,
+        idle_timeout: Option<Duration>,
+        vad: vad::Config,
+    ) -> Self {
+        let frames = idle_timeout.map(|_| vad::Frames::new(&vad));
+
+        Self {
+            vad,
+            max_samples: max_duration.map(samples),
+            idle_frames: idle_timeout
+                .zip(frames.as_ref())
+                .map(|(d, frames)| samples(d).div_ceil(frames.size())),
+            endpointer: None,
+            frames,
+            samples: 0,
+            silent_frames: 0,
+            fired: false,
+        }
+    }
+
+    /// Also stops once the speaker has finished, as `timing` judges it.
+    #[must_use]
+    pub fn with_endpointing(mut self, timing: Endpointing) -> Self {
+        let frames = self
+            .frames
+            .get_or_insert_with(|| vad::Frames::new(&self.vad));
+        self.endpointer = Some(Endpointer::new(timing, frames.frame_duration()));
+        self
+    }
+
+    /// Whether there's anything to watch for.
+    #[must_use]
+    pub fn is_active(&self) -> bool {
+        self.max_samples.is_some() || self.idle_frames.is_some() || self.endpointer.is_some()
+    }
+
+    /// Accounts for the next block of audio, returning why the recording should stop the first
+    /// time it should.
+    pub fn push(&mut self, block: &[f32]) -> Option<StopReason> {
+        if self.fired || !self.is_active() {
+            return None;
+        }
+
+        self.samples += block.len();
+        let reason = if self.max_samples.is_some_and(|max| self.samples >= max) {
+            Some(StopReason::MaxDuration)
+        } else {
+            self.detect_silence(block)
+        };
+
+        self.fired = reason.is_some();
+        reason
+    }
+
+    fn detect_silence(&mut self, block: &[f32]) -> Option<StopReason> {
+        let voiced = self.frames.as_mut()?.push(block);
+
+        let (mut idle, mut finished) = (false, false);
+        for voiced in voiced {
+            idle |= self.frame(voiced);
+            finished |= self
+                .endpointer
+                .as_mut()
+                .is_some_and(|endpointer| endpointer.frame(voiced));
+        }
+
+        if finished {
+            Some(StopReason::EndOfSpeech)
+        } else {
+            idle.then_some(StopReason::Idle)
+        }
+    }
+
+    /// Counts one VAD frame, returning whether the silence has now lasted long enough.
+    fn frame(&mut self, voiced: bool) -> bool {
+        self.silent_frames = if voiced { 0 } else { self.silent_frames + 1 };
+        self.idle_frames
+            .is_some_and(|frames| self.silent_frames >= frames)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_max_duration_fires_once() {
+        let mut watch = AutoStop::new(
+            Some(Duration::from_millis(100)),
+            None,
+            vad::Config::default(),
+        );
+        assert!(watch.is_active());
+        assert_eq!(watch.push(&[0.0; 1000]), None);
+        assert_eq!(watch.push(&[0.0; 600]), Some(StopReason::MaxDuration));
+        assert_eq!(watch.push(&[0.0; 600]), None);
+    }
+
+    #[test]
+    fn test_silence_is_idle() {
+        let mut watch = AutoStop::new(
+            None,
+            Some(Duration::from_millis(200)),
+            vad::Config::default(),
+        );
+        // One frame short of the timeout
+        assert_eq!(watch.push(&[0.0; 3000]), None);
+        assert_eq!(watch.push(&[0.0; 200]), Some(StopReason::Idle));
+    }
+
+    #[test]
+    fn test_speech_resets_idle_timeout() {
+        let mut watch = AutoStop::new(
+            None,
+            Some(Duration::from_millis(60)),
+            vad::Config::default(),
+        );
+        // 20ms frames
+        assert!(!watch.frame(false));
+        assert!(!watch.frame(false));
+        assert!(!watch.frame(true));
+        assert!(!watch.frame(false));
+        assert!(!watch.frame(false));
+        assert!(watch.frame(false));
+    }
+
+    #[test]
+    #[allow(clippy::cast_precision_loss)]
+    fn test_end_of_speech() {
+        let mut watch = AutoStop::new(None, None, vad::Config::default())
+            .with_endpointing(Endpointing::new(300));
+        assert!(watch.is_active());
+        // Silence before any speech is left alone
+        assert_eq!(watch.push(&vec![0.0; 16_000]), None);
+        let tone: Vec<f32> = (0..8000).map(|i| 0.5 * (i as f32 * 0.17).sin()).collect();
+        assert_eq!(watch.push(&tone), None);
+        assert_eq!(watch.push(&vec![0.0; 4000]), None);
+        assert_eq!(watch.push(&vec![0.0; 1000]), Some(StopReason::EndOfSpeech));
+    }
+
+    #[test]
+    fn test_no_limits() {
+        let mut watch = AutoStop::new(None, None, vad::Config::default());
+        assert!(!watch.is_active());
+        assert_eq!(watch.push(&vec![0.0; 16_000 * 60]), None);
+    }
+}
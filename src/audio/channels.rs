@@ -0,0 +1,178 @@
+use serde::{Deserialize, Serialize};
+
+use super::process::InputSample;
+
+#[derive(Debug, thiserror::Error)]
+pub enum Error {
+    #[error("channel numbers start at 1")]
+    Zero,
+
+    #[error("channel {0} selected but the input only has {1}")]
+    OutOfRange(u16, u16),
+
+    #[error("input has no channels")]
+    NoChannels,
+}
+
+/// How the selected channels are combined into the mono signal whisper receives.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, clap::ValueEnum, Deserialize, Serialize)]
+pub enum Downmix {
+    /// Mean of the selected channels
+    #[default]
+    #[serde(rename = "average")]
+    Average,
+
+    /// Sum of the selected channels, clipped to full scale
+    #[serde(rename = "sum")]
+    Sum,
+
+    /// Whichever selected channel carries the most energy in each device callback
+    #[serde(rename = "strongest")]
+    Strongest,
+}
+
+/// Reduces interleaved frames of any width to mono.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ChannelMix {
+    width: usize,
+    selected: Vec<usize>,
+    downmix: Downmix,
+    scale: f32,
+}
+
+impl ChannelMix {
+    /// `selection` holds 1-based channel numbers, as audio interfaces label their inputs. When
+    /// it's `None` or empty, every channel is used.
+    pub fn new(channels: u16, selection: Option<&[u16]>, downmix: Downmix) -> Result<Self, Error> {
+        if channels == 0 {
+            return Err(Error::NoChannels);
+        }
+
+        let selected = match selection {
+            Some(selection) if !selection.is_empty() => selection
+                .iter()
+                .map(|&n| match n {
+                    0 => Err(Error::Zero),
+                    n if n > channels => Err(Error::OutOfRange(n, channels)),
+                    n => Ok(usize::from(n - 1)),
+                })
+                .collect::<Result<Vec<_>, _>>()?,
+            _ => (0..usize::from(channels)).collect(),
+        };
+
+        #[allow(clippy::cast_precision_loss)]
+        let scale = 1.0 / selected.len() as f32;
+
+        Ok(Self {
+            width: usize::from(channels),
+            selected,
+            downmix,
+            scale,
+        })
+    }
+
+    /// Fewest channels a device needs for `selection` to be satisfiable.
+    #[must_use]
+    pub fn required_channels(selection: Option<&[u16]>) -> u16 {
+        selection.and_then(|s| s.iter().copied().max()).unwrap_or(1)
+    }
+
+    #[must_use]
+    pub fn width(&self) -> usize {
+        self.width
+    }
+
+    /// Replaces the contents of `out` with one mono sample per complete frame of `input`.
+    pub fn downmix_into<I: InputSample>(&self, input: &[I], out: &mut Vec<f32>) {
+        out.clear();
+        let frames = input.chunks_exact(self.width);
+
+        match self.downmix {
+            Downmix::Average => out.extend(frames.map(|f| self.sum(f) * self.scale)),
+            Downmix::Sum => out.extend(frames.map(|f| self.sum(f).clamp(-1.0, 1.0))),
+            Downmix::Strongest => {
+                let channel = self.strongest(input);
+                out.extend(frames.map(|f| f[channel].to_f32()));
+            }
+        }
+    }
+
+    fn sum<I: InputSample>(&self, frame: &[I]) -> f32 {
+        self.selected.iter().map(|&c| frame[c].to_f32()).sum()
+    }
+
+    fn strongest<I: InputSample>(&self, input: &[I]) -> usize {
+        self.selected
+            .iter()
+            .copied()
+            .map(|c| {
+                let energy: f32 = input
+                    .iter()
+                    .skip(c)
+                    .step_by(self.width)
+                    .map(|s| s.to_f32().powi(2))
+                    .sum();
+                (c, energy)
+            })
+            .max_by(|(_, a), (_, b)| a.total_cmp(b))
+            .map_or(0, |(c, _)| c)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const FOUR_CHANNELS: [f32; 8] = [0.1, 0.2, 0.3, 0.4, 0.5, 0.6, 0.7, 0.8];
+
+    fn mix(selection: Option<&[u16]>, downmix: Downmix, input: &[f32]) -> Vec<f32> {
+        let mut out = Vec::new();
+        ChannelMix::new(4, selection, downmix)
+            .unwrap()
+            .downmix_into(input, &mut out);
+        out
+    }
+
+    #[test]
+    fn test_single_channel() {
+        assert_eq!(
+            mix(Some(&[3]), Downmix::Average, &FOUR_CHANNELS),
+            vec![0.3, 0.7]
+        );
+    }
+
+    #[test]
+    fn test_average_pair() {
+        let out = mix(Some(&[1, 2]), Downmix::Average, &FOUR_CHANNELS);
+        assert!((out[0] - 0.15).abs() < 1e-6);
+        assert!((out[1] - 0.55).abs() < 1e-6);
+    }
+
+    #[test]
+    fn test_sum_clips() {
+        let out = mix(None, Downmix::Sum, &FOUR_CHANNELS);
+        assert!((out[0] - 1.0).abs() < 1e-6);
+        assert!((out[1] - 1.0).abs() < 1e-6);
+    }
+
+    #[test]
+    fn test_strongest() {
+        let input = [0.0, 0.9, 0.0, 0.0, 0.1, -0.8, 0.0, 0.0];
+        assert_eq!(
+            mix(Some(&[1, 2]), Downmix::Strongest, &input),
+            vec![0.9, -0.8]
+        );
+    }
+
+    #[test]
+    fn test_out_of_range() {
+        assert!(matches!(
+            ChannelMix::new(2, Some(&[3]), Downmix::Average),
+            Err(Error::OutOfRange(3, 2))
+        ));
+        assert!(matches!(
+            ChannelMix::new(2, Some(&[0]), Downmix::Average),
+            Err(Error::Zero)
+        ));
+    }
+}
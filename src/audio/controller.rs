@@ -1,6 +1,7 @@
 use std::{
     fmt::Debug,
     sync::{Arc, Condvar, Mutex},
+    time::{Duration, Instant},
 };
 
 #[derive(Debug, Clone)]
@@ -13,7 +14,7 @@ impl<T: Debug + Clone + PartialEq> Notifier<T> {
         let mut state = lock.lock().unwrap();
         log::trace!("Locked (notify)");
         *state = value;
-        cvar.notify_one();
+        cvar.notify_all();
     }
 
     pub fn wait_until(&self, value: &T) {
@@ -32,18 +33,77 @@ impl<T: Debug + Clone + PartialEq> Notifier<T> {
     }
 }
 
+impl<T: Debug + Clone> Notifier<T> {
+    /// Waits until `done` accepts the value, giving up at `deadline`. Returns the accepted
+    /// value, or `None` on timeout.
+    pub fn wait_deadline(&self, deadline: Instant, done: impl Fn(&T) -> bool) -> Option<T> {
+        let (lock, cvar) = &*self.0;
+        let mut state = lock.lock().unwrap();
+        while !done(&state) {
+            let timeout = deadline.saturating_duration_since(Instant::now());
+            if timeout.is_zero() {
+                log::trace!("Timed out waiting, last value: {:?}", &*state);
+                return None;
+            }
+            state = cvar.wait_timeout(state, timeout).unwrap().0;
+        }
+        Some(state.clone())
+    }
+
+    /// Waits without a deadline until `done` accepts the value, returning it.
+    pub fn wait_match(&self, done: impl Fn(&T) -> bool) -> T {
+        let (lock, cvar) = &*self.0;
+        let mut state = lock.lock().unwrap();
+        while !done(&state) {
+            state = cvar.wait(state).unwrap();
+        }
+        state.clone()
+    }
+
+    /// Replaces the value with whatever `f` returns for it, if anything.
+    pub fn update(&self, f: impl FnOnce(&T) -> Option<T>) {
+        let (lock, cvar) = &*self.0;
+        let mut state = lock.lock().unwrap();
+        if let Some(value) = f(&state) {
+            *state = value;
+            cvar.notify_all();
+        }
+    }
+}
+
 impl<T: Clone + Default> Notifier<T> {
     pub fn new() -> Self {
         Self(Arc::new((Mutex::new(T::default()), Condvar::new())))
     }
 }
 
-#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
 pub enum RecordState {
+    /// Neither started nor stopped yet
     #[default]
+    Idle,
     Stopped,
     Started,
     Recording,
+
+    /// The recording thread has returned after being stopped
+    Exited,
+
+    /// The recording thread has returned with an error, or panicked
+    Failed(String),
+}
+
+impl RecordState {
+    fn finished(&self) -> bool {
+        matches!(self, Self::Exited | Self::Failed(_))
+    }
+}
+
+/// Why a wait on the recording thread ended without reaching the state it wanted.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum WaitError {
+    Failed(String),
+    Timeout,
 }
 
 #[derive(Debug, Clone)]
@@ -59,7 +119,9 @@ impl Controller {
     }
 
     pub fn start(&self) {
-        self.notifier.notify(RecordState::Started);
+        // The thread may already have failed opening the device, which the caller needs to see
+        self.notifier
+            .update(|state| (!state.finished()).then_some(RecordState::Started));
     }
 
     pub fn recording(&self) {
@@ -67,10 +129,159 @@ impl Controller {
     }
 
     pub fn stop(&self) {
-        self.notifier.notify(RecordState::Stopped);
+        // Don't hide a failure the caller hasn't seen yet
+        self.notifier
+            .update(|state| (!state.finished()).then_some(RecordState::Stopped));
+    }
+
+    pub fn fail(&self, reason: String) {
+        self.notifier.notify(RecordState::Failed(reason));
+    }
+
+    pub fn wait_for(&self, state: &RecordState) {
+        self.notifier.wait_until(state);
+    }
+
+    /// Called from the recording thread, blocking while the recording is idle. Returns `false`
+    /// if it was stopped before it was started.
+    pub fn wait_for_start(&self) -> bool {
+        self.notifier
+            .wait_match(|s| matches!(s, RecordState::Started | RecordState::Stopped))
+            == RecordState::Started
+    }
+
+    /// Waits until recording has begun.
+    pub fn wait_for_recording(&self, timeout: Duration) -> Result<(), WaitError> {
+        let state = self
+            .notifier
+            .wait_deadline(Instant::now() + timeout, |s| {
+                matches!(s, RecordState::Recording) || s.finished()
+            })
+            .ok_or(WaitError::Timeout)?;
+
+        match state {
+            RecordState::Recording => Ok(()),
+            RecordState::Failed(reason) => Err(WaitError::Failed(reason)),
+            _ => Err(WaitError::Failed(
+                "recording thread exited before it started recording".to_string(),
+            )),
+        }
+    }
+
+    /// Waits until the recording thread has returned, however it went.
+    pub fn wait_for_exit(&self, timeout: Duration) -> Result<(), WaitError> {
+        self.notifier
+            .wait_deadline(Instant::now() + timeout, RecordState::finished)
+            .map(|_| ())
+            .ok_or(WaitError::Timeout)
+    }
+
+    /// Marks the recording thread as finished when dropped: failed if it's unwinding from a
+    /// panic, exited otherwise unless it already reported a failure.
+    pub fn exit_guard(&self) -> ExitGuard {
+        ExitGuard(self.clone())
+    }
+}
+
+pub struct ExitGuard(Controller);
+
+impl Drop for ExitGuard {
+    fn drop(&mut self) {
+        if std::thread::panicking() {
+            self.0.fail("recording thread panicked".to_string());
+        } else {
+            self.0.notifier.update(|state| {
+                (!matches!(state, RecordState::Failed(_))).then_some(RecordState::Exited)
+            });
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::thread;
+
+    use super::*;
+
+    #[test]
+    fn test_wait_for_recording_times_out() {
+        let controller = Controller::new();
+        controller.start();
+        assert_eq!(
+            controller.wait_for_recording(Duration::from_millis(10)),
+            Err(WaitError::Timeout)
+        );
+    }
+
+    #[test]
+    fn test_failure_reaches_waiter() {
+        let controller = Controller::new();
+        let c2 = controller.clone();
+        let handle = thread::spawn(move || {
+            let _guard = c2.exit_guard();
+            assert!(c2.wait_for_start());
+            c2.fail("no device".to_string());
+        });
+
+        controller.start();
+        assert_eq!(
+            controller.wait_for_recording(Duration::from_secs(5)),
+            Err(WaitError::Failed("no device".to_string()))
+        );
+        handle.join().unwrap();
+
+        // Stopping afterwards doesn't overwrite the failure
+        controller.stop();
+        assert_eq!(controller.wait_for_exit(Duration::ZERO), Ok(()));
+    }
+
+    #[test]
+    fn test_wait_for_start_blocks_until_started() {
+        let controller = Controller::new();
+        let c2 = controller.clone();
+        let (tx, rx) = std::sync::mpsc::channel();
+        let handle = thread::spawn(move || tx.send(c2.wait_for_start()).unwrap());
+
+        assert!(rx.recv_timeout(Duration::from_millis(50)).is_err());
+        controller.start();
+        assert_eq!(rx.recv_timeout(Duration::from_secs(5)), Ok(true));
+        handle.join().unwrap();
+
+        let controller = Controller::new();
+        controller.stop();
+        assert!(!controller.wait_for_start());
+    }
+
+    #[test]
+    fn test_failure_before_start_is_kept() {
+        let controller = Controller::new();
+        let c2 = controller.clone();
+        let handle = thread::spawn(move || {
+            let _guard = c2.exit_guard();
+            c2.fail("no matching device".to_string());
+        });
+        handle.join().unwrap();
+
+        controller.start();
+        assert_eq!(
+            controller.wait_for_recording(Duration::ZERO),
+            Err(WaitError::Failed("no matching device".to_string()))
+        );
+        assert_eq!(controller.wait_for_exit(Duration::ZERO), Ok(()));
     }
 
-    pub fn wait_for(&self, state: RecordState) {
-        self.notifier.wait_until(&state);
+    #[test]
+    fn test_panic_is_a_failure() {
+        let controller = Controller::new();
+        let c2 = controller.clone();
+        let handle = thread::spawn(move || {
+            let _guard = c2.exit_guard();
+            panic!("boom");
+        });
+        assert!(handle.join().is_err());
+        assert_eq!(
+            controller.wait_for_recording(Duration::ZERO),
+            Err(WaitError::Failed("recording thread panicked".to_string()))
+        );
     }
 }
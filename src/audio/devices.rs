@@ -0,0 +1,329 @@
+use std::fmt;
+
+use cpal::{
+    traits::{DeviceTrait, HostTrait},
+    DevicesError,
+};
+use itertools::Itertools;
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, thiserror::Error)]
+pub enum Error {
+    #[error("device index must be a number, got {0:?}")]
+    Index(String),
+
+    #[error("invalid device pattern: {0}")]
+    Regex(#[from] regex::Error),
+
+    #[error("no audio host named {0:?}, available: {1}")]
+    UnknownHost(String, String),
+
+    #[error("audio host unavailable: {0}")]
+    HostUnavailable(#[from] cpal::HostUnavailable),
+
+    #[error("audio device error: {0}")]
+    Devices(#[from] DevicesError),
+}
+
+/// The host called `name`, compared case-insensitively (e.g. "jack" or "ALSA"), or the
+/// platform's default host.
+pub fn host_id(name: Option<&str>) -> Result<cpal::HostId, Error> {
+    let Some(name) = name else {
+        return Ok(cpal::default_host().id());
+    };
+
+    let available = cpal::available_hosts();
+    available
+        .iter()
+        .copied()
+        .find(|id| id.name().eq_ignore_ascii_case(name))
+        .ok_or_else(|| {
+            Error::UnknownHost(
+                name.to_string(),
+                available.iter().map(cpal::HostId::name).join(", "),
+            )
+        })
+}
+
+/// Connects to the host [`host_id`] picks for `name`.
+pub fn host(name: Option<&str>) -> Result<cpal::Host, Error> {
+    Ok(cpal::host_from_id(host_id(name)?)?)
+}
+
+/// An audio host cpal was built with and can currently reach.
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize, Serialize)]
+pub struct HostInfo {
+    name: String,
+    is_default: bool,
+}
+
+impl HostInfo {
+    #[must_use]
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    #[must_use]
+    pub fn is_default(&self) -> bool {
+        self.is_default
+    }
+}
+
+impl fmt::Display for HostInfo {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let default = if self.is_default { " (default)" } else { "" };
+        write!(f, "{}{default}", self.name)
+    }
+}
+
+/// Lists the hosts sessions can select with [`host`].
+#[must_use]
+pub fn list_hosts() -> Vec<HostInfo> {
+    let default = cpal::default_host().id();
+    cpal::available_hosts()
+        .into_iter()
+        .map(|id| HostInfo {
+            name: id.name().to_string(),
+            is_default: id == default,
+        })
+        .collect()
+}
+
+/// How a session's device pattern is compared against input device names.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, clap::ValueEnum, Deserialize, Serialize)]
+pub enum DeviceMatch {
+    /// Name contains the pattern
+    #[default]
+    #[serde(rename = "contains")]
+    Contains,
+
+    /// Name is exactly the pattern
+    #[serde(rename = "exact")]
+    Exact,
+
+    /// Pattern is the device's index, as listed by `devices`
+    #[serde(rename = "index")]
+    Index,
+
+    /// Name matches the pattern as a regular expression
+    #[serde(rename = "regex")]
+    Regex,
+}
+
+/// A device pattern parsed according to its [`DeviceMatch`].
+#[derive(Debug, Clone)]
+pub enum Selector {
+    Any,
+    Contains(String),
+    Exact(String),
+    Index(usize),
+    Regex(Regex),
+}
+
+impl Selector {
+    pub fn new(pattern: Option<&str>, kind: DeviceMatch) -> Result<Self, Error> {
+        let Some(pattern) = pattern else {
+            return Ok(Self::Any);
+        };
+
+        Ok(match kind {
+            DeviceMatch::Contains => Self::Contains(pattern.to_string()),
+            DeviceMatch::Exact => Self::Exact(pattern.to_string()),
+            DeviceMatch::Index => Self::Index(
+                pattern
+                    .trim()
+                    .parse()
+                    .map_err(|_| Error::Index(pattern.to_string()))?,
+            ),
+            DeviceMatch::Regex => Self::Regex(Regex::new(pattern)?),
+        })
+    }
+
+    /// `index` is the device's position in the host's input device list.
+    #[must_use]
+    pub fn matches(&self, index: usize, name: &str) -> bool {
+        match self {
+            Self::Any => true,
+            Self::Contains(pattern) => name.contains(pattern.as_str()),
+            Self::Exact(pattern) => name == pattern,
+            Self::Index(i) => index == *i,
+            Self::Regex(re) => re.is_match(name),
+        }
+    }
+}
+
+/// One of the stream configurations an input device supports.
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize, Serialize)]
+pub struct ConfigRange {
+    channels: u16,
+    sample_format: String,
+    min_sample_rate: u32,
+    max_sample_rate: u32,
+    /// `None` when the host doesn't report buffer size limits
+    buffer_size: Option<(u32, u32)>,
+}
+
+impl From<&cpal::SupportedStreamConfigRange> for ConfigRange {
+    fn from(config: &cpal::SupportedStreamConfigRange) -> Self {
+        Self {
+            channels: config.channels(),
+            sample_format: format!("{:?}", config.sample_format()).to_lowercase(),
+            min_sample_rate: config.min_sample_rate().0,
+            max_sample_rate: config.max_sample_rate().0,
+            buffer_size: match config.buffer_size() {
+                cpal::SupportedBufferSize::Range { min, max } => Some((*min, *max)),
+                cpal::SupportedBufferSize::Unknown => None,
+            },
+        }
+    }
+}
+
+impl fmt::Display for ConfigRange {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "{}ch {} {}-{} Hz",
+            self.channels, self.sample_format, self.min_sample_rate, self.max_sample_rate
+        )?;
+        if let Some((min, max)) = self.buffer_size {
+            write!(f, ", buffer {min}-{max}")?;
+        }
+        Ok(())
+    }
+}
+
+/// An input device as the host reports it.
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize, Serialize)]
+pub struct DeviceInfo {
+    index: usize,
+    name: String,
+    is_default: bool,
+    configs: Vec<ConfigRange>,
+}
+
+impl DeviceInfo {
+    #[must_use]
+    pub fn index(&self) -> usize {
+        self.index
+    }
+
+    #[must_use]
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    #[must_use]
+    pub fn is_default(&self) -> bool {
+        self.is_default
+    }
+
+    #[must_use]
+    pub fn configs(&self) -> &[ConfigRange] {
+        &self.configs
+    }
+}
+
+impl fmt::Display for DeviceInfo {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let default = if self.is_default { " (default)" } else { "" };
+        write!(f, "{}: {}{default}", self.index, self.name)?;
+        for config in &self.configs {
+            write!(f, "\n    {config}")?;
+        }
+        Ok(())
+    }
+}
+
+/// Lists the input devices of the host called `host`, or the default host, indexed in the order
+/// [`Selector`] counts them.
+pub fn list_devices(host: Option<&str>) -> Result<Vec<DeviceInfo>, Error> {
+    let host = self::host(host)?;
+    let default_name = host.default_input_device().and_then(|d| d.name().ok());
+
+    Ok(host
+        .input_devices()?
+        .enumerate()
+        .map(|(index, d)| {
+            let name = d
+                .name()
+                .unwrap_or_else(|_| format!("Unnamed device #{index}"));
+            let configs = d
+                .supported_input_configs()
+                .map(|configs| configs.map(|c| ConfigRange::from(&c)).collect())
+                .unwrap_or_default();
+
+            DeviceInfo {
+                index,
+                is_default: default_name.as_deref() == Some(name.as_str()),
+                name,
+                configs,
+            }
+        })
+        .collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_selectors() {
+        let name = "MacBook Pro Microphone";
+
+        let select = |pattern, kind| Selector::new(Some(pattern), kind).unwrap();
+        assert!(select("MacBook", DeviceMatch::Contains).matches(0, name));
+        assert!(!select("MacBook", DeviceMatch::Exact).matches(0, name));
+        assert!(select(name, DeviceMatch::Exact).matches(0, name));
+        assert!(select("2", DeviceMatch::Index).matches(2, name));
+        assert!(!select("2", DeviceMatch::Index).matches(1, name));
+        assert!(select("^MacBook.*Microphone$", DeviceMatch::Regex).matches(0, name));
+        assert!(Selector::new(None, DeviceMatch::Exact)
+            .unwrap()
+            .matches(5, name));
+    }
+
+    #[test]
+    fn test_invalid_patterns() {
+        assert!(matches!(
+            Selector::new(Some("first"), DeviceMatch::Index),
+            Err(Error::Index(_))
+        ));
+        assert!(matches!(
+            Selector::new(Some("("), DeviceMatch::Regex),
+            Err(Error::Regex(_))
+        ));
+    }
+
+    #[test]
+    fn test_host_lookup() {
+        let default = cpal::default_host().id();
+        assert_eq!(host_id(None).unwrap(), default);
+        assert_eq!(
+            host_id(Some(&default.name().to_lowercase())).unwrap(),
+            default
+        );
+        assert!(matches!(
+            host_id(Some("no such host")),
+            Err(Error::UnknownHost(..))
+        ));
+    }
+
+    #[test]
+    fn test_serialize_device() {
+        let device = DeviceInfo {
+            index: 0,
+            name: "Mic".to_string(),
+            is_default: true,
+            configs: vec![ConfigRange {
+                channels: 1,
+                sample_format: "f32".to_string(),
+                min_sample_rate: 16_000,
+                max_sample_rate: 48_000,
+                buffer_size: None,
+            }],
+        };
+        let expected = r#"{"index":0,"name":"Mic","is_default":true,"configs":[{"channels":1,"sample_format":"f32","min_sample_rate":16000,"max_sample_rate":48000,"buffer_size":null}]}"#;
+        assert_eq!(serde_json::to_string(&device).unwrap(), expected);
+    }
+}
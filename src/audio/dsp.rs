@@ -0,0 +1,509 @@
+use std::{collections::VecDeque, f32::consts::PI, sync::Arc};
+
+use rustfft::{num_complex::Complex, Fft, FftPlanner};
+use serde::{Deserialize, Serialize};
+
+/// Which processing stages to run on a recording before it's transcribed. Everything is off
+/// by default.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, clap::Args, Deserialize, Serialize)]
+#[serde(default)]
+pub struct DspConfig {
+    /// Filter out rumble and hum below this frequency, in Hz (e.g. 80)
+    #[clap(long)]
+    high_pass_hz: Option<u32>,
+
+    /// Mute audio quieter than this, in dBFS (e.g. -50)
+    #[clap(long, allow_negative_numbers = true)]
+    noise_gate_db: Option<i32>,
+
+    /// Subtract a running estimate of steady background noise, such as a fan
+    #[clap(long)]
+    denoise: bool,
+
+    /// Bring speech to a consistent level, so quiet mics aren't lost
+    #[clap(long)]
+    normalize: bool,
+}
+
+impl DspConfig {
+    #[must_use]
+    pub fn with_high_pass(mut self, hz: u32) -> Self {
+        self.high_pass_hz = Some(hz);
+        self
+    }
+
+    #[must_use]
+    pub fn with_noise_gate(mut self, db: i32) -> Self {
+        self.noise_gate_db = Some(db);
+        self
+    }
+
+    #[must_use]
+    pub fn with_denoise(mut self) -> Self {
+        self.denoise = true;
+        self
+    }
+
+    #[must_use]
+    pub fn with_normalize(mut self) -> Self {
+        self.normalize = true;
+        self
+    }
+
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        *self == Self::default()
+    }
+
+    /// The enabled stages for audio at `sample_rate`, in the order they run: filtering and
+    /// noise reduction first, so the gate and normalization see mostly speech.
+    #[must_use]
+    #[allow(clippy::cast_precision_loss)]
+    pub fn chain(&self, sample_rate: u32) -> Chain {
+        let rate = sample_rate as f32;
+        let mut stages: Vec<Box<dyn Stage>> = Vec::new();
+        if let Some(hz) = self.high_pass_hz {
+            stages.push(Box::new(HighPass::new(hz as f32, rate)));
+        }
+        if self.denoise {
+            stages.push(Box::new(Denoise::new()));
+        }
+        if let Some(db) = self.noise_gate_db {
+            stages.push(Box::new(NoiseGate::new(db as f32, rate)));
+        }
+        if self.normalize {
+            stages.push(Box::new(Normalize::new(rate)));
+        }
+        Chain(stages)
+    }
+}
+
+/// One step of a [`Chain`], applied in place to each block of resampled audio.
+pub trait Stage: Send + Sync {
+    fn process(&mut self, block: &mut [f32]);
+
+    /// How many samples the stage delays its output by.
+    fn latency(&self) -> usize {
+        0
+    }
+}
+
+/// A sequence of [`Stage`]s, built from a [`DspConfig`].
+#[derive(Default)]
+pub struct Chain(Vec<Box<dyn Stage>>);
+
+impl Chain {
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+
+    pub fn process(&mut self, block: &mut [f32]) {
+        for stage in &mut self.0 {
+            stage.process(block);
+        }
+    }
+
+    /// Pushes out the audio still delayed inside the chain once the input has ended.
+    pub fn finish(&mut self) -> Vec<f32> {
+        let latency = self.0.iter().map(|s| s.latency()).sum();
+        let mut tail = vec![0.0; latency];
+        self.process(&mut tail);
+        tail
+    }
+}
+
+/// Coefficient for a one-pole smoother that moves about 63% of the way to its target in
+/// `ms` milliseconds at `rate` samples per second.
+fn smoothing(ms: f32, rate: f32) -> f32 {
+    (-1000.0 / (ms * rate)).exp()
+}
+
+fn amplitude(db: f32) -> f32 {
+    10_f32.powf(db / 20.0)
+}
+
+/// Second-order Butterworth high-pass filter, with the coefficients from the Audio EQ
+/// Cookbook.
+struct HighPass {
+    b: [f32; 3],
+    a: [f32; 2],
+    x: [f32; 2],
+    y: [f32; 2],
+}
+
+impl HighPass {
+    fn new(cutoff_hz: f32, rate: f32) -> Self {
+        let w0 = 2.0 * PI * cutoff_hz.clamp(1.0, rate / 2.0 - 1.0) / rate;
+        let alpha = w0.sin() / 2.0_f32.sqrt();
+        let cos = w0.cos();
+        let a0 = 1.0 + alpha;
+        let b0 = 1.0_f32.midpoint(cos) / a0;
+        Self {
+            b: [b0, -2.0 * b0, b0],
+            a: [-2.0 * cos / a0, (1.0 - alpha) / a0],
+            x: [0.0; 2],
+            y: [0.0; 2],
+        }
+    }
+}
+
+impl Stage for HighPass {
+    fn process(&mut self, block: &mut [f32]) {
+        for sample in block {
+            let x = *sample;
+            let y = self.b[0] * x + self.b[1] * self.x[0] + self.b[2] * self.x[1]
+                - self.a[0] * self.y[0]
+                - self.a[1] * self.y[1];
+            self.x = [x, self.x[0]];
+            self.y = [y, self.y[0]];
+            *sample = y;
+        }
+    }
+}
+
+/// Mutes the input while its level stays under a threshold, holding open briefly after speech
+/// so word endings aren't clipped.
+struct NoiseGate {
+    threshold: f32,
+    decay: f32,
+    open: f32,
+    close: f32,
+    /// Samples the gate stays open for after the level drops
+    hold_samples: usize,
+    envelope: f32,
+    gain: f32,
+    hold: usize,
+}
+
+/// How long the gate stays open after the level drops.
+const GATE_HOLD_MS: f32 = 150.0;
+
+impl NoiseGate {
+    #[allow(clippy::cast_possible_truncation, clippy::cast_sign_loss)]
+    fn new(threshold_db: f32, rate: f32) -> Self {
+        Self {
+            threshold: amplitude(threshold_db),
+            decay: smoothing(20.0, rate),
+            open: smoothing(1.0, rate),
+            close: smoothing(50.0, rate),
+            hold_samples: (GATE_HOLD_MS * rate / 1000.0) as usize,
+            envelope: 0.0,
+            gain: 0.0,
+            hold: 0,
+        }
+    }
+}
+
+impl Stage for NoiseGate {
+    fn process(&mut self, block: &mut [f32]) {
+        for sample in block {
+            self.envelope = sample.abs().max(self.envelope * self.decay);
+            if self.envelope >= self.threshold {
+                self.hold = self.hold_samples;
+            } else {
+                self.hold = self.hold.saturating_sub(1);
+            }
+
+            let (target, coefficient) = if self.hold > 0 {
+                (1.0, self.open)
+            } else {
+                (0.0, self.close)
+            };
+            self.gain = target + (self.gain - target) * coefficient;
+            *sample *= self.gain;
+        }
+    }
+}
+
+/// Automatic gain control towards a fixed speech level.
+struct Normalize {
+    measure: f32,
+    adjust: f32,
+    power: f32,
+    gain: f32,
+}
+
+/// Level speech is brought to.
+const NORMALIZE_TARGET_DB: f32 = -20.0;
+
+/// Most the input is amplified by, so noise isn't pulled up to speech level.
+const NORMALIZE_MAX_GAIN_DB: f32 = 30.0;
+
+/// Input quieter than this is left at the current gain rather than measured.
+const NORMALIZE_FLOOR_DB: f32 = -60.0;
+
+impl Normalize {
+    fn new(rate: f32) -> Self {
+        Self {
+            measure: smoothing(300.0, rate),
+            adjust: smoothing(500.0, rate),
+            power: 0.0,
+            gain: 1.0,
+        }
+    }
+}
+
+impl Stage for Normalize {
+    fn process(&mut self, block: &mut [f32]) {
+        let floor = amplitude(NORMALIZE_FLOOR_DB);
+        let target = amplitude(NORMALIZE_TARGET_DB);
+        let max_gain = amplitude(NORMALIZE_MAX_GAIN_DB);
+
+        for sample in block {
+            let x = *sample;
+            if x.abs() > floor {
+                self.power = x * x + (self.power - x * x) * self.measure;
+            }
+            let rms = self.power.sqrt();
+            if rms > floor {
+                let wanted = (target / rms).clamp(1.0 / max_gain, max_gain);
+                self.gain = wanted + (self.gain - wanted) * self.adjust;
+            }
+            *sample = (x * self.gain).clamp(-1.0, 1.0);
+        }
+    }
+}
+
+/// Samples per spectral frame (32ms).
+const FRAME: usize = 512;
+const HOP: usize = FRAME / 2;
+
+/// How hard the noise estimate is subtracted from each bin.
+const OVERSUBTRACT: f32 = 2.0;
+
+/// Least a bin is scaled by, which keeps the leftover noise from turning into warbling tones.
+const SPECTRAL_FLOOR: f32 = 0.1;
+
+/// Per-frame growth of the noise estimate, so it can follow noise that gets louder.
+const NOISE_RISE: f32 = 1.005;
+
+/// Weight of the previous frames in each bin's smoothed power.
+const POWER_SMOOTHING: f32 = 0.8;
+
+/// Corrects for the minimum of the smoothed power sitting below its average.
+const MINIMUM_BIAS: f32 = 1.5;
+
+/// Spectral subtraction against a minimum-tracking noise estimate, with 50% overlapping
+/// square-root Hann frames.
+struct Denoise {
+    window: Vec<f32>,
+    input: VecDeque<f32>,
+    /// Samples received since the last frame was processed
+    fresh: usize,
+    overlap: Vec<f32>,
+    output: VecDeque<f32>,
+    /// Smoothed power per bin
+    power: Option<Vec<f32>>,
+    /// Minimum of the smoothed power per bin
+    noise: Vec<f32>,
+    forward: Arc<dyn Fft<f32>>,
+    inverse: Arc<dyn Fft<f32>>,
+    spectrum: Vec<Complex<f32>>,
+}
+
+impl Denoise {
+    #[allow(clippy::cast_precision_loss)]
+    fn new() -> Self {
+        let mut planner = FftPlanner::new();
+        Self {
+            window: (0..FRAME)
+                .map(|i| (PI * i as f32 / FRAME as f32).sin())
+                .collect(),
+            input: std::iter::repeat_n(0.0, FRAME).collect(),
+            fresh: 0,
+            overlap: vec![0.0; FRAME],
+            output: std::iter::repeat_n(0.0, HOP).collect(),
+            power: None,
+            noise: vec![0.0; FRAME / 2 + 1],
+            forward: planner.plan_fft_forward(FRAME),
+            inverse: planner.plan_fft_inverse(FRAME),
+            spectrum: vec![Complex::default(); FRAME],
+        }
+    }
+
+    #[allow(clippy::cast_precision_loss)]
+    fn frame(&mut self) {
+        for ((bin, x), w) in self.spectrum.iter_mut().zip(&self.input).zip(&self.window) {
+            *bin = Complex::new(x * w, 0.0);
+        }
+        self.forward.process(&mut self.spectrum);
+
+        let first = self.power.is_none();
+        let power = self.power.get_or_insert_with(|| vec![0.0; FRAME / 2 + 1]);
+        for (k, smoothed) in power.iter_mut().enumerate() {
+            let bin = self.spectrum[k].norm_sqr();
+            *smoothed = if first {
+                bin
+            } else {
+                bin + (*smoothed - bin) * POWER_SMOOTHING
+            };
+            self.noise[k] = if first {
+                *smoothed
+            } else {
+                (self.noise[k] * NOISE_RISE).min(*smoothed)
+            };
+
+            let magnitude = bin.sqrt();
+            let noise = (self.noise[k] * MINIMUM_BIAS).sqrt();
+            let gain = if magnitude > 0.0 {
+                (1.0 - OVERSUBTRACT * noise / magnitude).max(SPECTRAL_FLOOR)
+            } else {
+                SPECTRAL_FLOOR
+            };
+            self.spectrum[k] *= gain;
+            // Keep the spectrum conjugate-symmetric so the output stays real
+            if k != 0 && k != FRAME / 2 {
+                self.spectrum[FRAME - k] *= gain;
+            }
+        }
+        self.inverse.process(&mut self.spectrum);
+
+        // The inverse transform isn't normalised
+        let scale = 1.0 / FRAME as f32;
+        for ((o, bin), w) in self
+            .overlap
+            .iter_mut()
+            .zip(&self.spectrum)
+            .zip(&self.window)
+        {
+            *o += bin.re * scale * w;
+        }
+        self.output.extend(self.overlap.drain(..HOP));
+        self.overlap.extend(std::iter::repeat_n(0.0, HOP));
+    }
+}
+
+impl Stage for Denoise {
+    fn process(&mut self, block: &mut [f32]) {
+        for sample in block {
+            self.input.pop_front();
+            self.input.push_back(*sample);
+            self.fresh += 1;
+            if self.fresh == HOP {
+                self.fresh = 0;
+                self.frame();
+            }
+            *sample = self.output.pop_front().unwrap_or_default();
+        }
+    }
+
+    fn latency(&self) -> usize {
+        FRAME
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SAMPLE_RATE: f32 = 16_000.0;
+
+    #[allow(clippy::cast_precision_loss)]
+    fn sine(hz: f32, amplitude: f32, samples: usize) -> Vec<f32> {
+        (0..samples)
+            .map(|i| amplitude * (2.0 * PI * hz * i as f32 / SAMPLE_RATE).sin())
+            .collect()
+    }
+
+    /// Deterministic white noise in [-amplitude, amplitude].
+    #[allow(clippy::cast_precision_loss)]
+    fn noise(amplitude: f32, samples: usize) -> Vec<f32> {
+        let mut state = 0x1234_5678_u32;
+        (0..samples)
+            .map(|_| {
+                state = state.wrapping_mul(1_664_525).wrapping_add(1_013_904_223);
+                amplitude * ((state >> 8) as f32 / (1 << 23) as f32 - 1.0)
+            })
+            .collect()
+    }
+
+    #[allow(clippy::cast_precision_loss)]
+    fn rms(samples: &[f32]) -> f32 {
+        (samples.iter().map(|s| s * s).sum::<f32>() / samples.len() as f32).sqrt()
+    }
+
+    fn db(ratio: f32) -> f32 {
+        20.0 * ratio.log10()
+    }
+
+    /// Runs `input` through `stage` in device-sized blocks and returns the last second.
+    fn settled(stage: &mut dyn Stage, mut input: Vec<f32>) -> Vec<f32> {
+        for block in input.chunks_mut(170) {
+            stage.process(block);
+        }
+        input.split_off(input.len() - 16_000)
+    }
+
+    #[test]
+    fn test_high_pass() {
+        let mut filter = HighPass::new(200.0, SAMPLE_RATE);
+        let hum = settled(&mut filter, sine(50.0, 0.5, 32_000));
+        assert!(db(rms(&hum) / rms(&sine(50.0, 0.5, 16_000))) < -18.0);
+
+        let mut filter = HighPass::new(200.0, SAMPLE_RATE);
+        let voice = settled(&mut filter, sine(1000.0, 0.5, 32_000));
+        assert!(db(rms(&voice) / rms(&sine(1000.0, 0.5, 16_000))).abs() < 0.5);
+    }
+
+    #[test]
+    fn test_noise_gate() {
+        let mut gate = NoiseGate::new(-50.0, SAMPLE_RATE);
+        let hiss = settled(&mut gate, noise(amplitude(-70.0), 32_000));
+        assert!(db(rms(&hiss)) < -100.0);
+
+        let mut gate = NoiseGate::new(-50.0, SAMPLE_RATE);
+        let tone = sine(440.0, 0.25, 32_000);
+        let passed = settled(&mut gate, tone.clone());
+        assert!(db(rms(&passed) / rms(&tone)).abs() < 0.1);
+    }
+
+    #[test]
+    fn test_normalize() {
+        let mut agc = Normalize::new(SAMPLE_RATE);
+        let quiet = settled(&mut agc, sine(440.0, amplitude(-40.0), 5 * 16_000));
+        assert!((db(rms(&quiet)) - NORMALIZE_TARGET_DB).abs() < 3.0);
+
+        let mut agc = Normalize::new(SAMPLE_RATE);
+        let loud = settled(&mut agc, sine(440.0, 0.9, 5 * 16_000));
+        assert!((db(rms(&loud)) - NORMALIZE_TARGET_DB).abs() < 3.0);
+
+        // Near-silence isn't pulled up to speech level
+        let mut agc = Normalize::new(SAMPLE_RATE);
+        let silence = settled(&mut agc, noise(amplitude(-80.0), 5 * 16_000));
+        assert!(db(rms(&silence)) < -70.0);
+    }
+
+    #[test]
+    fn test_denoise() {
+        let background = noise(0.02, 5 * 16_000);
+        let mut denoise = Denoise::new();
+        let reduced = settled(&mut denoise, background.clone());
+        assert!(db(rms(&reduced) / rms(&background)) < -10.0);
+
+        // A tone well above the noise floor survives
+        let mut input = background;
+        let tone = sine(700.0, 0.3, 16_000);
+        for (x, t) in input[4 * 16_000..].iter_mut().zip(&tone) {
+            *x += t;
+        }
+        let mut denoise = Denoise::new();
+        let kept = settled(&mut denoise, input);
+        // Skip the frames still holding the noise from before the tone
+        let kept = &kept[2 * FRAME..];
+        assert!(db(rms(kept) / rms(&tone)).abs() < 1.5);
+    }
+
+    #[test]
+    fn test_chain_flushes_delayed_audio() {
+        let mut chain = DspConfig::default().with_denoise().chain(16_000);
+        let mut input = sine(440.0, 0.5, 8000);
+        chain.process(&mut input);
+        let tail = chain.finish();
+        assert_eq!(tail.len(), FRAME);
+        assert!(rms(&tail) > 0.0);
+
+        assert!(DspConfig::default().chain(16_000).is_empty());
+        assert!(DspConfig::default().is_empty());
+    }
+}
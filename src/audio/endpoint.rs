@@ -0,0 +1,331 @@
+use std::time::Duration;
+
+use serde::{Deserialize, Serialize};
+
+use super::{samples, vad};
+
+/// When a hands-free recording decides the speaker has finished.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, Serialize)]
+#[allow(clippy::struct_field_names)]
+pub struct Endpointing {
+    /// Silence after speech that ends the recording
+    trailing_silence_ms: u32,
+    /// Shortest run of speech that counts, so a cough or a click doesn't start the countdown
+    min_speech_ms: u32,
+    /// Gaps in speech up to this long don't break a run of it
+    hangover_ms: u32,
+}
+
+impl Endpointing {
+    #[must_use]
+    pub fn new(trailing_silence_ms: u32) -> Self {
+        Self {
+            trailing_silence_ms,
+            min_speech_ms: 200,
+            hangover_ms: 100,
+        }
+    }
+
+    #[must_use]
+    pub fn with_min_speech_ms(mut self, ms: u32) -> Self {
+        self.min_speech_ms = ms;
+        self
+    }
+
+    #[must_use]
+    pub fn with_hangover_ms(mut self, ms: u32) -> Self {
+        self.hangover_ms = ms;
+        self
+    }
+
+    #[must_use]
+    pub fn trailing_silence(&self) -> Duration {
+        Duration::from_millis(self.trailing_silence_ms.into())
+    }
+
+    #[must_use]
+    pub fn min_speech(&self) -> Duration {
+        Duration::from_millis(self.min_speech_ms.into())
+    }
+
+    #[must_use]
+    pub fn hangover(&self) -> Duration {
+        Duration::from_millis(self.hangover_ms.into())
+    }
+}
+
+/// Follows a stream of VAD frames to find where the speaker finished: a run of speech at least
+/// the minimum length, followed by the trailing silence.
+#[derive(Debug)]
+pub struct Endpointer {
+    min_speech: usize,
+    hangover: usize,
+    trailing: usize,
+    /// Voiced frames in the current run of speech
+    speech: usize,
+    /// Unvoiced frames since the last voiced one
+    gap: usize,
+    /// Whether a run has been long enough to count as speech
+    heard: bool,
+}
+
+impl Endpointer {
+    /// `frame` is how much audio each VAD decision covers.
+    #[must_use]
+    pub fn new(timing: Endpointing, frame: Duration) -> Self {
+        let frames = |d: Duration| {
+            usize::try_from(d.as_millis().div_ceil(frame.as_millis().max(1))).unwrap_or(usize::MAX)
+        };
+        Self {
+            min_speech: frames(timing.min_speech()).max(1),
+            hangover: frames(timing.hangover()),
+            trailing: frames(timing.trailing_silence()).max(1),
+            speech: 0,
+            gap: 0,
+            heard: false,
+        }
+    }
+
+    /// Whether any speech has been heard yet.
+    #[must_use]
+    pub fn heard_speech(&self) -> bool {
+        self.heard
+    }
+
+    /// Whether there's no speech so far, not even a run too short yet to count.
+    #[must_use]
+    pub fn is_idle(&self) -> bool {
+        !self.heard && self.speech == 0
+    }
+
+    /// Accounts for one frame, returning whether the speaker has now finished.
+    pub fn frame(&mut self, voiced: bool) -> bool {
+        if voiced {
+            self.speech += 1;
+            self.gap = 0;
+            self.heard |= self.speech >= self.min_speech;
+            return false;
+        }
+
+        self.gap += 1;
+        if self.gap > self.hangover {
+            self.speech = 0;
+        }
+        self.heard && self.gap >= self.trailing
+    }
+
+    /// Starts listening for the next utterance.
+    pub fn reset(&mut self) {
+        self.speech = 0;
+        self.gap = 0;
+        self.heard = false;
+    }
+}
+
+/// Cuts a continuous stream of audio into utterances, each ending where an [`Endpointer`]
+/// judges the speaker to have finished.
+///
+/// Stays on the thread that created it, as its [`vad::Frames`] must.
+pub struct Segmenter {
+    frames: vad::Frames,
+    endpointer: Endpointer,
+    /// Audio since the end of the last utterance
+    buffer: Vec<f32>,
+    /// How much of `buffer` the VAD has judged
+    judged: usize,
+    /// Samples kept ahead of speech while waiting for it to start
+    lead: usize,
+}
+
+impl Segmenter {
+    #[must_use]
+    pub fn new(timing: Endpointing, vad: &vad::Config) -> Self {
+        let frames = vad::Frames::new(vad);
+        let frame = frames.frame_duration();
+        let lead = frames.size()
+            * usize::try_from(timing.trailing_silence().as_millis() / frame.as_millis().max(1))
+                .unwrap_or_default();
+        Self {
+            endpointer: Endpointer::new(timing, frame),
+            frames,
+            buffer: Vec::new(),
+            judged: 0,
+            lead,
+        }
+    }
+
+    /// Accounts for the next block of audio, returning the utterances it completes.
+    pub fn push(&mut self, block: &[f32]) -> Vec<Vec<f32>> {
+        self.buffer.extend_from_slice(block);
+
+        let mut utterances = Vec::new();
+        for voiced in self.frames.push(block) {
+            self.judged += self.frames.size();
+            if self.endpointer.frame(voiced) {
+                utterances.push(self.buffer.drain(..self.judged).collect());
+                self.judged = 0;
+                self.endpointer.reset();
+            } else if self.endpointer.is_idle() && self.judged > self.lead {
+                // Nobody has spoken yet, so there's no need to keep all the silence
+                let excess = self.judged - self.lead;
+                self.buffer.drain(..excess);
+                self.judged -= excess;
+            }
+        }
+        utterances
+    }
+
+    /// The audio after the last complete utterance, which may hold the start of another.
+    #[must_use]
+    pub fn into_remainder(self) -> Vec<f32> {
+        self.buffer
+    }
+}
+
+/// Silence kept either side of a [`SpeechClips`] clip.
+const CLIP_MARGIN: Duration = Duration::from_millis(100);
+
+/// Cuts short bursts of speech out of a live stream, such as someone saying a wake phrase,
+/// leaving out anything longer.
+///
+/// Stays on its thread, like the [`vad::VoiceDetector`] inside it.
+pub struct SpeechClips {
+    detector: vad::VoiceDetector,
+    /// Recent audio, starting `offset` samples into the stream
+    buffer: Vec<f32>,
+    offset: usize,
+    max: Duration,
+}
+
+impl SpeechClips {
+    /// Clips speech no longer than `max`.
+    #[must_use]
+    pub fn new(vad: &vad::Config, max: Duration) -> Self {
+        Self {
+            detector: vad::VoiceDetector::new(vad),
+            buffer: Vec::new(),
+            offset: 0,
+            max,
+        }
+    }
+
+    /// Accounts for the next block of audio, returning the clips of speech it completes.
+    pub fn push(&mut self, block: &[f32]) -> Vec<Vec<f32>> {
+        self.buffer.extend_from_slice(block);
+
+        let mut clips = Vec::new();
+        for segment in self.detector.push(block) {
+            if !segment.is_speech() || segment.duration() > self.max {
+                continue;
+            }
+            let start = samples(segment.start().saturating_sub(CLIP_MARGIN)).max(self.offset);
+            let end = samples(segment.end() + CLIP_MARGIN).min(self.offset + self.buffer.len());
+            if start < end {
+                clips.push(self.buffer[start - self.offset..end - self.offset].to_vec());
+            }
+        }
+
+        // Twice the longest clip leaves room for the silence it takes to end one
+        let keep = samples(self.max * 2 + CLIP_MARGIN);
+        if self.buffer.len() > keep {
+            let excess = self.buffer.len() - keep;
+            self.buffer.drain(..excess);
+            self.offset += excess;
+        }
+        clips
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const FRAME: Duration = Duration::from_millis(20);
+
+    #[allow(clippy::cast_precision_loss)]
+    fn tone(samples: usize) -> Vec<f32> {
+        (0..samples)
+            .map(|i| 0.5 * (2.0 * std::f32::consts::PI * 440.0 * i as f32 / 16_000.0).sin())
+            .collect()
+    }
+
+    fn run(endpointer: &mut Endpointer, voiced: bool, frames: usize) -> bool {
+        (0..frames).fold(false, |ended, _| endpointer.frame(voiced) || ended)
+    }
+
+    #[test]
+    fn test_silence_after_speech_ends() {
+        let mut endpointer = Endpointer::new(Endpointing::new(400), FRAME);
+        // Silence before anyone speaks never ends the recording
+        assert!(!run(&mut endpointer, false, 100));
+        assert!(!run(&mut endpointer, true, 20));
+        assert!(endpointer.heard_speech());
+        assert!(!run(&mut endpointer, false, 19));
+        assert!(endpointer.frame(false));
+    }
+
+    #[test]
+    fn test_short_noise_is_not_speech() {
+        let mut endpointer = Endpointer::new(Endpointing::new(400).with_hangover_ms(0), FRAME);
+        // 100ms bursts, shorter than the 200ms minimum
+        for _ in 0..5 {
+            assert!(!run(&mut endpointer, true, 5));
+            assert!(!run(&mut endpointer, false, 30));
+        }
+        assert!(!endpointer.heard_speech());
+    }
+
+    #[test]
+    fn test_hangover_bridges_gaps() {
+        let timing = Endpointing::new(400).with_hangover_ms(100);
+        let mut endpointer = Endpointer::new(timing, FRAME);
+        // Two 120ms syllables with a 60ms gap make one 240ms run
+        run(&mut endpointer, true, 6);
+        run(&mut endpointer, false, 3);
+        run(&mut endpointer, true, 6);
+        assert!(endpointer.heard_speech());
+
+        let mut endpointer = Endpointer::new(timing.with_hangover_ms(0), FRAME);
+        run(&mut endpointer, true, 6);
+        run(&mut endpointer, false, 3);
+        run(&mut endpointer, true, 6);
+        assert!(!endpointer.heard_speech());
+    }
+
+    #[test]
+    fn test_segmenter_cuts_utterances() {
+        let mut segmenter = Segmenter::new(Endpointing::new(300), &vad::Config::default());
+        // A long wait before speaking only keeps the lead-in
+        assert!(segmenter.push(&vec![0.0; 48_000]).is_empty());
+
+        let mut audio = tone(8000);
+        audio.extend(vec![0.0; 8000]);
+        audio.extend(tone(12_000));
+        audio.extend(vec![0.0; 8000]);
+        audio.extend(tone(4000));
+        let utterances = segmenter.push(&audio);
+        assert_eq!(utterances.len(), 2);
+        // 300ms of lead-in, the speech and the trailing silence
+        assert_eq!(utterances[0].len(), 4800 + 8000 + 4800);
+        assert!(utterances[1].len() > 12_000 && utterances[1].len() < 12_000 + 8000 + 4800);
+
+        let remainder = segmenter.into_remainder();
+        assert!(remainder.len() >= 4000 && remainder.len() < 4000 + 8000);
+    }
+
+    #[test]
+    fn test_speech_clips_skip_long_speech() {
+        let mut clips = SpeechClips::new(&vad::Config::default(), Duration::from_secs(2));
+        let mut audio = vec![0.0; 96_000];
+        audio.extend(tone(16_000));
+        audio.extend(vec![0.0; 16_000]);
+        audio.extend(tone(48_000));
+        audio.extend(vec![0.0; 16_000]);
+
+        let found: Vec<_> = audio.chunks(1000).flat_map(|b| clips.push(b)).collect();
+        assert_eq!(found.len(), 1);
+        // The second of speech with 100ms either side
+        assert_eq!(found[0].len(), 19_200);
+        assert!(found[0][..1600].iter().all(|&s| s == 0.0));
+    }
+}
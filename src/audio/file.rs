@@ -0,0 +1,175 @@
+use std::{
+    ffi::OsStr,
+    path::{Path, PathBuf},
+};
+
+#[derive(Debug, thiserror::Error)]
+pub enum Error {
+    #[error("wav error: {0}")]
+    Wav(#[from] hound::Error),
+
+    #[error("flac error: {0}")]
+    Flac(#[from] claxon::Error),
+
+    #[error("unsupported audio file: {0}")]
+    Unsupported(PathBuf),
+}
+
+/// Decoded contents of an audio file, kept as interleaved `f32` samples so they can be fed
+/// through the same processing path as a live input device.
+#[derive(Debug, Clone)]
+pub struct AudioFile {
+    config: cpal::StreamConfig,
+    samples: Vec<f32>,
+}
+
+impl AudioFile {
+    /// Decodes the file at `path`, choosing the decoder from its extension.
+    pub fn open(path: &Path) -> Result<Self, Error> {
+        let extension = path
+            .extension()
+            .and_then(OsStr::to_str)
+            .map(str::to_ascii_lowercase);
+
+        match extension.as_deref() {
+            Some("wav" | "wave") => Self::read_wav(path),
+            Some("flac") => Self::read_flac(path),
+            _ => Err(Error::Unsupported(path.to_owned())),
+        }
+    }
+
+    #[must_use]
+    pub fn config(&self) -> &cpal::StreamConfig {
+        &self.config
+    }
+
+    #[must_use]
+    pub fn samples(&self) -> &[f32] {
+        &self.samples
+    }
+
+    fn read_wav(path: &Path) -> Result<Self, Error> {
+        let reader = hound::WavReader::open(path)?;
+        let spec = reader.spec();
+
+        let samples = match spec.sample_format {
+            hound::SampleFormat::Float => reader.into_samples::<f32>().collect::<Result<_, _>>()?,
+            hound::SampleFormat::Int => {
+                let scale = int_scale(u32::from(spec.bits_per_sample));
+                reader
+                    .into_samples::<i32>()
+                    .map(|s| s.map(|s| int_to_float(s, scale)))
+                    .collect::<Result<_, _>>()?
+            }
+        };
+
+        Ok(Self {
+            config: stream_config(spec.channels, spec.sample_rate),
+            samples,
+        })
+    }
+
+    fn read_flac(path: &Path) -> Result<Self, Error> {
+        let mut reader = claxon::FlacReader::open(path)?;
+        let info = reader.streaminfo();
+        let scale = int_scale(info.bits_per_sample);
+
+        let samples = reader
+            .samples()
+            .map(|s| s.map(|s| int_to_float(s, scale)))
+            .collect::<Result<_, _>>()?;
+
+        let channels = u16::try_from(info.channels).unwrap_or(u16::MAX);
+        Ok(Self {
+            config: stream_config(channels, info.sample_rate),
+            samples,
+        })
+    }
+}
+
+fn stream_config(channels: u16, sample_rate: u32) -> cpal::StreamConfig {
+    cpal::StreamConfig {
+        channels,
+        sample_rate: cpal::SampleRate(sample_rate),
+        buffer_size: cpal::BufferSize::Default,
+    }
+}
+
+#[allow(clippy::cast_precision_loss)]
+fn int_scale(bits_per_sample: u32) -> f32 {
+    (1_i64 << bits_per_sample.saturating_sub(1)) as f32
+}
+
+#[allow(clippy::cast_precision_loss)]
+fn int_to_float(sample: i32, scale: f32) -> f32 {
+    sample as f32 / scale
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A 16-bit stereo 44.1 kHz FLAC stream: STREAMINFO, then one frame holding two verbatim
+    /// subframes, so the left channel is `[0, -16384]` and the right `[16384, -32768]`.
+    const STEREO_FLAC: [u8; 61] = [
+        // "fLaC", then STREAMINFO with its MD5 left unset
+        0x66, 0x4c, 0x61, 0x43, 0x80, 0x00, 0x00, 0x22, 0x00, 0x10, 0x00, 0x10, 0x00, 0x00, 0x00,
+        0x00, 0x00, 0x00, 0x0a, 0xc4, 0x42, 0xf0, 0x00, 0x00, 0x00, 0x02, 0x00, 0x00, 0x00, 0x00,
+        0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+        // Frame header, two verbatim subframes and the footer CRC
+        0xff, 0xf8, 0x69, 0x18, 0x00, 0x01, 0xb8, 0x02, 0x00, 0x00, 0xc0, 0x00, 0x02, 0x40, 0x00,
+        0x80, 0x00, 0x56, 0xf5,
+    ];
+
+    /// A path in the temp dir that concurrent test runs won't share.
+    fn scratch_path(name: &str) -> PathBuf {
+        std::env::temp_dir().join(format!("voice-file-{}-{name}", std::process::id()))
+    }
+
+    fn write_wav(name: &str, spec: hound::WavSpec, samples: &[i16]) -> PathBuf {
+        let path = scratch_path(name);
+        let mut writer = hound::WavWriter::create(&path, spec).unwrap();
+        for s in samples {
+            writer.write_sample(*s).unwrap();
+        }
+        writer.finalize().unwrap();
+        path
+    }
+
+    #[test]
+    fn test_read_int_wav() {
+        let spec = hound::WavSpec {
+            channels: 2,
+            sample_rate: 44100,
+            bits_per_sample: 16,
+            sample_format: hound::SampleFormat::Int,
+        };
+        let path = write_wav("read-int.wav", spec, &[0, 16384, -16384, i16::MIN]);
+
+        let file = AudioFile::open(&path).unwrap();
+        assert_eq!(file.config().channels, 2);
+        assert_eq!(file.config().sample_rate.0, 44100);
+        assert_eq!(file.samples(), &[0.0, 0.5, -0.5, -1.0]);
+
+        std::fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn test_read_flac() {
+        let path = scratch_path("read.FLAC");
+        std::fs::write(&path, STEREO_FLAC).unwrap();
+
+        let file = AudioFile::open(&path).unwrap();
+        assert_eq!(file.config().channels, 2);
+        assert_eq!(file.config().sample_rate.0, 44100);
+        assert_eq!(file.samples(), &[0.0, 0.5, -0.5, -1.0]);
+
+        std::fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn test_unsupported_extension() {
+        let result = AudioFile::open(Path::new("clip.mp3"));
+        assert!(matches!(result, Err(Error::Unsupported(_))));
+    }
+}
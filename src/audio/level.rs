@@ -0,0 +1,208 @@
+use std::fmt;
+
+use serde::{Deserialize, Serialize};
+
+use super::OUTPUT_SAMPLE_RATE;
+
+/// Samples per level reading: 50ms of resampled output.
+const WINDOW: usize = OUTPUT_SAMPLE_RATE as usize / 20;
+
+/// Samples at least this loud are taken to have been clipped on the way in.
+const CLIP: f32 = 0.99;
+
+/// Windows quieter than this (about -60 dBFS) are near enough to silence that the wrong input
+/// may be selected.
+const SILENCE_RMS: f32 = 0.001;
+
+/// Loudness expressed in dB relative to full scale, floored at -100.
+#[must_use]
+pub fn dbfs(amplitude: f32) -> f32 {
+    (20.0 * amplitude.log10()).max(-100.0)
+}
+
+/// Input level over one window of a recording.
+#[derive(Debug, Clone, Copy, PartialEq, Deserialize, Serialize)]
+pub struct Level {
+    rms: f32,
+    peak: f32,
+    clipped: bool,
+    silent: bool,
+}
+
+impl Level {
+    #[must_use]
+    pub fn rms(&self) -> f32 {
+        self.rms
+    }
+
+    #[must_use]
+    pub fn peak(&self) -> f32 {
+        self.peak
+    }
+
+    #[must_use]
+    pub fn clipped(&self) -> bool {
+        self.clipped
+    }
+
+    #[must_use]
+    pub fn silent(&self) -> bool {
+        self.silent
+    }
+}
+
+impl fmt::Display for Level {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "rms {:6.1} dBFS, peak {:6.1} dBFS",
+            dbfs(self.rms),
+            dbfs(self.peak)
+        )?;
+        if self.clipped {
+            write!(f, " CLIPPED")?;
+        }
+        if self.silent {
+            write!(f, " SILENT")?;
+        }
+        Ok(())
+    }
+}
+
+/// Input levels over a whole recording.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Deserialize, Serialize)]
+pub struct LevelSummary {
+    windows: usize,
+    rms: f32,
+    peak: f32,
+    clipped_windows: usize,
+    silent_windows: usize,
+}
+
+impl LevelSummary {
+    #[must_use]
+    pub fn rms(&self) -> f32 {
+        self.rms
+    }
+
+    #[must_use]
+    pub fn peak(&self) -> f32 {
+        self.peak
+    }
+
+    #[must_use]
+    pub fn clipped_windows(&self) -> usize {
+        self.clipped_windows
+    }
+
+    /// Whether every window was near silence, which usually means the wrong input.
+    #[must_use]
+    pub fn is_silent(&self) -> bool {
+        self.windows > 0 && self.silent_windows == self.windows
+    }
+}
+
+/// Turns a recording's resampled audio into a [`Level`] per window and a running summary.
+#[derive(Debug, Default)]
+pub struct LevelMeter {
+    sum_squares: f64,
+    peak: f32,
+    samples: usize,
+    summary: LevelSummary,
+    total_squares: f64,
+    total_samples: usize,
+}
+
+impl LevelMeter {
+    #[must_use]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Accounts for the next block of audio, returning the levels of the windows it completes.
+    pub fn push(&mut self, block: &[f32]) -> Vec<Level> {
+        let mut levels = Vec::new();
+        for &sample in block {
+            self.sum_squares += f64::from(sample * sample);
+            self.peak = self.peak.max(sample.abs());
+            self.samples += 1;
+            if self.samples == WINDOW {
+                levels.push(self.close_window());
+            }
+        }
+        levels
+    }
+
+    #[must_use]
+    pub fn summary(&self) -> LevelSummary {
+        self.summary
+    }
+
+    #[allow(clippy::cast_possible_truncation, clippy::cast_precision_loss)]
+    fn close_window(&mut self) -> Level {
+        let rms = (self.sum_squares / self.samples as f64).sqrt() as f32;
+        let level = Level {
+            rms,
+            peak: self.peak,
+            clipped: self.peak >= CLIP,
+            silent: rms < SILENCE_RMS,
+        };
+
+        self.total_squares += self.sum_squares;
+        self.total_samples += self.samples;
+        let summary = &mut self.summary;
+        summary.windows += 1;
+        summary.rms = (self.total_squares / self.total_samples as f64).sqrt() as f32;
+        summary.peak = summary.peak.max(level.peak);
+        summary.clipped_windows += usize::from(level.clipped);
+        summary.silent_windows += usize::from(level.silent);
+
+        self.sum_squares = 0.0;
+        self.peak = 0.0;
+        self.samples = 0;
+        level
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_levels_per_window() {
+        let mut meter = LevelMeter::new();
+        assert!(meter.push(&[0.5; WINDOW - 1]).is_empty());
+
+        let levels = meter.push(&[-0.5; WINDOW + 1]);
+        assert_eq!(levels.len(), 2);
+        assert!((levels[0].rms() - 0.5).abs() < 1e-6);
+        assert!((levels[0].peak() - 0.5).abs() < 1e-6);
+        assert!(!levels[0].clipped());
+        assert!(!levels[0].silent());
+    }
+
+    #[test]
+    fn test_clipping_and_silence() {
+        let mut meter = LevelMeter::new();
+        let mut block = vec![0.0; WINDOW];
+        block[10] = 1.0;
+        let clipped = meter.push(&block);
+        let silent = meter.push(&[0.0; WINDOW]);
+        assert!(clipped[0].clipped());
+        assert!(silent[0].silent());
+
+        let summary = meter.summary();
+        assert_eq!(summary.clipped_windows(), 1);
+        assert!(!summary.is_silent());
+        assert!((summary.peak() - 1.0).abs() < 1e-6);
+    }
+
+    #[test]
+    fn test_silent_recording() {
+        let mut meter = LevelMeter::new();
+        assert!(!meter.summary().is_silent());
+        meter.push(&[0.0001; WINDOW * 3]);
+        assert!(meter.summary().is_silent());
+        assert!(dbfs(meter.summary().rms()) < -60.0);
+    }
+}
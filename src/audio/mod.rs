@@ -1,46 +1,43 @@
+use std::time::Duration;
+
+pub mod dsp;
+pub mod pcm;
+pub mod resample;
 pub mod vad;
 
+mod autostop;
+mod channels;
 mod controller;
+mod devices;
+mod endpoint;
+mod file;
+mod level;
+mod monitor;
 mod process;
 mod recording;
 
-pub use process::AudioMessage;
-
-pub use recording::{Error as RecordingError, Recording, Session};
+pub use autostop::{AutoStop, StopReason};
+pub use channels::Downmix;
+pub use devices::{list_devices, list_hosts, ConfigRange, DeviceInfo, DeviceMatch, HostInfo};
+pub use endpoint::{Endpointer, Endpointing, Segmenter, SpeechClips};
+pub use level::{dbfs, Level, LevelMeter, LevelSummary};
+pub use monitor::{Monitor, Tap};
+pub use process::{AudioMessage, Captured, Error as ProcessError, Gate};
 
-use cpal::{
-    traits::{DeviceTrait, HostTrait},
-    DevicesError,
+pub use recording::{
+    Error as RecordingError, InputInfo, Metadata, Recording, RecordingInfo, Session, StreamInfo,
 };
 
-pub trait MySample: Send + hound::Sample + cpal::Sample + 'static {}
-impl<S> MySample for S where S: Send + hound::Sample + cpal::Sample + 'static {}
-
-pub fn list_channels() -> Result<(), DevicesError> {
-    let host = cpal::default_host();
-    for (device_name, config) in host.input_devices()?.enumerate().flat_map(|(i, d)| {
-        let name = d.name().unwrap_or_else(|_| format!("Unnamed device #{i}"));
-        let configs: Vec<_> = d
-            .supported_input_configs()
-            .map(std::iter::Iterator::collect)
-            .unwrap_or_default();
+/// Rate every recording is resampled to. whisper only takes 16 kHz input, so this isn't a
+/// setting: the monitor's history, the level meter, the archive and endpointing all count
+/// samples at this rate.
+pub const OUTPUT_SAMPLE_RATE: u32 = 16_000;
 
-        configs.into_iter().map(move |c| (name.clone(), c))
-    }) {
-        let (buf_floor, buf_ceil) = match config.buffer_size() {
-            cpal::SupportedBufferSize::Range { min, max } => (min, max),
-            cpal::SupportedBufferSize::Unknown => unimplemented!(),
-        };
-        println!(
-            "{}: sample_rate:{}-{}, sample_format:{:?}, channels:{}, buffer_size: {}-{}",
-            device_name,
-            config.min_sample_rate().0,
-            config.max_sample_rate().0,
-            config.sample_format(),
-            config.channels(),
-            buf_floor,
-            buf_ceil
-        );
-    }
-    Ok(())
+/// Samples of output audio in `d`.
+#[allow(clippy::cast_possible_truncation)]
+fn samples(d: Duration) -> usize {
+    (d.as_millis() as usize) * OUTPUT_SAMPLE_RATE as usize / 1000
 }
+
+pub trait MySample: Send + hound::Sample + cpal::Sample + 'static {}
+impl<S> MySample for S where S: Send + hound::Sample + cpal::Sample + 'static {}
@@ -0,0 +1,339 @@
+use std::{
+    sync::{Arc, Mutex},
+    thread,
+};
+
+use cpal::traits::{DeviceTrait, HostTrait, StreamTrait};
+use crossbeam::channel::{bounded, Sender};
+use ringbuffer::{AllocRingBuffer, RingBuffer};
+
+use super::{
+    channels::Downmix,
+    devices,
+    dsp::DspConfig,
+    process::AudioMessage,
+    recording::{open_stream, Error, Metadata, Session, SessionError},
+    OUTPUT_SAMPLE_RATE,
+};
+use crate::sync::ProcessNode;
+
+/// Output samples per millisecond after resampling.
+const SAMPLES_PER_MS: usize = OUTPUT_SAMPLE_RATE as usize / 1000;
+
+/// Receives each block of monitored audio and any stream errors; returning `false`
+/// unsubscribes it.
+type Subscriber = Box<dyn FnMut(AudioMessage<f32>) -> bool + Send>;
+
+struct Shared {
+    history: AllocRingBuffer<f32>,
+    /// How much of the history a new subscriber receives first
+    preroll: usize,
+    subscriber: Option<Subscriber>,
+    /// Gets every block, whether or not anything is recording
+    listener: Option<Subscriber>,
+}
+
+impl Shared {
+    fn new(preroll_ms: usize, history_ms: usize) -> Self {
+        let history = history_ms.max(preroll_ms) * SAMPLES_PER_MS;
+        Self {
+            history: AllocRingBuffer::new(history.max(1)),
+            preroll: preroll_ms * SAMPLES_PER_MS,
+            subscriber: None,
+            listener: None,
+        }
+    }
+
+    /// The last `samples` of the history, or all of it if there's less.
+    fn tail(&self, samples: usize) -> Vec<f32> {
+        let skip = self.history.len().saturating_sub(samples);
+        self.history.iter().skip(skip).copied().collect()
+    }
+
+    fn push(&mut self, block: Vec<f32>) {
+        self.history.extend(block.iter().copied());
+        if let Some(ref mut listener) = self.listener {
+            if !listener(AudioMessage::Data(block.clone())) {
+                self.listener = None;
+            }
+        }
+        self.forward(AudioMessage::Data(block));
+    }
+
+    fn forward(&mut self, msg: AudioMessage<f32>) {
+        if let Some(ref mut subscriber) = self.subscriber {
+            if !subscriber(msg) {
+                self.subscriber = None;
+            }
+        }
+    }
+
+    fn subscribe(&mut self, mut subscriber: Subscriber) {
+        let preroll = self.tail(self.preroll);
+        if preroll.is_empty() || subscriber(AudioMessage::Data(preroll)) {
+            self.subscriber = Some(subscriber);
+        }
+    }
+}
+
+/// An input device kept open for the daemon's lifetime, so a recording can start with the
+/// audio captured just before it was asked for, and recent audio can be transcribed after the
+/// fact.
+///
+/// The stream lives on its own thread, since cpal streams can't always be moved between
+/// threads, and stops when the monitor is dropped.
+pub struct Monitor {
+    shared: Arc<Mutex<Shared>>,
+    metadata: Metadata,
+    host: cpal::HostId,
+    device_index: Option<usize>,
+    rolling_ms: usize,
+    _stop: Sender<()>,
+}
+
+/// Cloneable handle for subscribing to a [`Monitor`] from a recording thread.
+#[derive(Clone)]
+pub struct Tap {
+    shared: Arc<Mutex<Shared>>,
+    metadata: Metadata,
+    host: cpal::HostId,
+    device_index: Option<usize>,
+}
+
+impl Monitor {
+    /// Opens the device `session` would record from. Recordings served by the monitor start
+    /// with `preroll_ms` of earlier audio, and [`Monitor::recall`] can reach back `rolling_ms`.
+    ///
+    /// # Panics
+    ///
+    /// when the stream thread exits without reporting whether the device opened
+    pub fn open(session: Session, preroll_ms: usize, rolling_ms: usize) -> Result<Self, Error> {
+        let host = devices::host_id(session.host()).map_err(SessionError::from)?;
+        let shared = Arc::new(Mutex::new(Shared::new(preroll_ms, rolling_ms)));
+
+        let node_shared = shared.clone();
+        let node = ProcessNode::new(move |it| {
+            for msg in it {
+                match msg {
+                    AudioMessage::Data(block) => node_shared.lock().unwrap().push(block),
+                    AudioMessage::Error(e) => {
+                        log::error!("Monitor error: {e}");
+                        node_shared.lock().unwrap().forward(AudioMessage::Error(e));
+                    }
+                }
+            }
+        });
+        let (sink, _) = node.run();
+
+        let (opened_send, opened) = bounded(1);
+        let (stop, stopped) = bounded::<()>(0);
+        thread::spawn(move || {
+            let open = || -> Result<(cpal::Stream, Metadata, Option<usize>), Error> {
+                let (device, supported_config) = session.preferred_config()?;
+                let device_name = device
+                    .name()
+                    .unwrap_or_else(|_| "Unnamed device".to_string());
+                // Kept so sessions selecting a device by index can still be served
+                let device_index = devices::host(session.host())
+                    .map_err(SessionError::from)?
+                    .input_devices()
+                    .map_err(SessionError::from)?
+                    .position(|d| d.name().is_ok_and(|n| n == device_name));
+                let cfg: cpal::StreamConfig = supported_config.clone().into();
+                let mix = session.channel_mix(cfg.channels)?;
+                // Recordings served from the monitor run their own processing, and the monitor
+                // only stops with the daemon, so its tail isn't worth flushing
+                let (stream, _) =
+                    open_stream(&device, &supported_config, mix, DspConfig::default(), sink)?;
+                stream.play()?;
+                let metadata = Metadata::new(device_name, cfg)
+                    .with_sample_format(supported_config.sample_format());
+                Ok((stream, metadata, device_index))
+            };
+
+            match open() {
+                Ok((stream, metadata, device_index)) => {
+                    let _ = opened_send.send(Ok((metadata, device_index)));
+                    // Blocks until the monitor is dropped
+                    let _ = stopped.recv();
+                    drop(stream);
+                }
+                Err(e) => {
+                    let _ = opened_send.send(Err(e));
+                }
+            }
+        });
+
+        let (metadata, device_index) = opened.recv().expect("monitor thread exited")?;
+        log::info!(
+            "Monitoring {} with {preroll_ms}ms of pre-roll and {rolling_ms}ms of rolling capture",
+            metadata.device_name()
+        );
+
+        Ok(Self {
+            shared,
+            metadata,
+            host,
+            device_index,
+            rolling_ms,
+            _stop: stop,
+        })
+    }
+
+    /// Up to the last `secs` seconds of audio, or `None` if rolling capture is disabled.
+    ///
+    /// # Panics
+    ///
+    /// when the monitor's lock is poisoned
+    #[must_use]
+    pub fn recall(&self, secs: u32) -> Option<Vec<f32>> {
+        if self.rolling_ms == 0 {
+            return None;
+        }
+        let ms = (secs as usize * 1000).min(self.rolling_ms);
+        Some(self.shared.lock().unwrap().tail(ms * SAMPLES_PER_MS))
+    }
+
+    /// Sends every block of audio from now on to `listener`, alongside any recording's
+    /// subscriber, until it returns `false`.
+    ///
+    /// # Panics
+    ///
+    /// when the monitor's lock is poisoned
+    pub fn listen(&self, listener: impl FnMut(AudioMessage<f32>) -> bool + Send + 'static) {
+        self.shared.lock().unwrap().listener = Some(Box::new(listener));
+    }
+
+    #[must_use]
+    pub fn tap(&self) -> Tap {
+        Tap {
+            shared: self.shared.clone(),
+            metadata: self.metadata.clone(),
+            host: self.host,
+            device_index: self.device_index,
+        }
+    }
+}
+
+impl Tap {
+    #[must_use]
+    pub fn metadata(&self) -> &Metadata {
+        &self.metadata
+    }
+
+    /// Whether `session` would record from the monitored device exactly as it's being
+    /// captured, so its audio can be taken from the monitor instead of a new stream.
+    #[must_use]
+    pub fn serves(&self, session: &Session) -> bool {
+        let config = self.metadata.config();
+        session.input_file().is_none()
+            && session.channels().is_none()
+            && session.downmix() == Downmix::default()
+            && session
+                .sample_rate()
+                .is_none_or(|rate| rate == config.sample_rate.0)
+            && devices::host_id(session.host()).is_ok_and(|host| host == self.host)
+            && session.device_selector().is_ok_and(|selector| {
+                let index = self.device_index.unwrap_or(usize::MAX);
+                selector.matches(index, self.metadata.device_name())
+            })
+    }
+
+    /// Sends the buffered pre-roll to `subscriber` as one block, followed by every block and
+    /// stream error until [`Tap::unsubscribe`] is called.
+    ///
+    /// # Panics
+    ///
+    /// when the monitor's lock is poisoned
+    pub fn subscribe(&self, subscriber: impl FnMut(AudioMessage<f32>) -> bool + Send + 'static) {
+        self.shared.lock().unwrap().subscribe(Box::new(subscriber));
+    }
+
+    /// # Panics
+    ///
+    /// when the monitor's lock is poisoned
+    pub fn unsubscribe(&self) {
+        self.shared.lock().unwrap().subscriber = None;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_subscriber_gets_preroll_then_live_audio() {
+        let mut shared = Shared::new(1, 0);
+        shared.push(vec![0.1; 10]);
+        shared.push(vec![0.2; 10]);
+
+        let (send, recv) = crossbeam::channel::unbounded();
+        shared.subscribe(Box::new(move |msg| match msg {
+            AudioMessage::Data(block) => send.send(block).is_ok(),
+            AudioMessage::Error(e) => panic!("{e}"),
+        }));
+        shared.push(vec![0.3; 4]);
+        shared.subscriber = None;
+        shared.push(vec![0.4; 4]);
+
+        let blocks: Vec<Vec<f32>> = recv.iter().collect();
+        // Only the last millisecond is kept
+        let mut preroll = vec![0.1; 6];
+        preroll.extend([0.2; 10]);
+        assert_eq!(blocks, vec![preroll, vec![0.3; 4]]);
+    }
+
+    #[test]
+    fn test_dropped_subscriber_is_removed() {
+        let mut shared = Shared::new(1, 0);
+        let (send, recv) = crossbeam::channel::unbounded();
+        shared.subscribe(Box::new(move |msg| match msg {
+            AudioMessage::Data(block) => send.send(block).is_ok(),
+            AudioMessage::Error(e) => panic!("{e}"),
+        }));
+        drop(recv);
+
+        shared.push(vec![0.5; 4]);
+        assert!(shared.subscriber.is_none());
+    }
+
+    #[test]
+    fn test_listener_gets_audio_between_recordings() {
+        let mut shared = Shared::new(1, 0);
+        let (send, recv) = crossbeam::channel::unbounded();
+        shared.listener = Some(Box::new(move |msg| match msg {
+            AudioMessage::Data(block) => send.send(block).is_ok(),
+            AudioMessage::Error(e) => panic!("{e}"),
+        }));
+
+        shared.push(vec![0.1; 4]);
+        shared.subscribe(Box::new(|_| true));
+        shared.push(vec![0.2; 4]);
+        shared.listener = None;
+
+        assert_eq!(
+            recv.iter().collect::<Vec<_>>(),
+            vec![vec![0.1; 4], vec![0.2; 4]]
+        );
+    }
+
+    #[test]
+    fn test_preroll_is_shorter_than_history() {
+        let mut shared = Shared::new(1, 2);
+        shared.push((0..40_u8).map(f32::from).collect());
+
+        let (send, recv) = crossbeam::channel::unbounded();
+        shared.subscribe(Box::new(move |msg| match msg {
+            AudioMessage::Data(block) => send.send(block).is_ok(),
+            AudioMessage::Error(e) => panic!("{e}"),
+        }));
+        shared.subscriber = None;
+
+        let preroll: Vec<f32> = (24..40_u8).map(f32::from).collect();
+        assert_eq!(recv.iter().collect::<Vec<_>>(), vec![preroll]);
+        assert_eq!(
+            shared.tail(usize::MAX),
+            (8..40_u8).map(f32::from).collect::<Vec<_>>()
+        );
+    }
+}
@@ -0,0 +1,281 @@
+use std::io::{self, Read};
+
+use serde::{Deserialize, Serialize};
+
+use super::{
+    channels::{self, ChannelMix, Downmix},
+    dsp::DspConfig,
+    process::{Captured, Process, Processor},
+    MySample,
+};
+use crate::sync::ProcessNode;
+
+/// Frames decoded per chunk, roughly matching a typical device callback.
+const CHUNK_FRAMES: usize = 1024;
+
+#[derive(Debug, thiserror::Error)]
+pub enum Error {
+    #[error("io error: {0}")]
+    Io(#[from] io::Error),
+
+    #[error("channel error: {0}")]
+    Channels(#[from] channels::Error),
+
+    #[error("failed to join processing thread")]
+    Sync,
+}
+
+/// Little-endian sample encodings, named as `ffmpeg` names them.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, clap::ValueEnum, Deserialize, Serialize)]
+pub enum PcmFormat {
+    #[default]
+    #[serde(rename = "s16le")]
+    S16le,
+
+    #[serde(rename = "s32le")]
+    S32le,
+
+    #[serde(rename = "f32le")]
+    F32le,
+}
+
+impl PcmFormat {
+    #[must_use]
+    pub fn bytes_per_sample(self) -> usize {
+        match self {
+            Self::S16le => 2,
+            Self::S32le | Self::F32le => 4,
+        }
+    }
+
+    #[allow(clippy::cast_precision_loss)]
+    fn decode(self, bytes: &[u8]) -> f32 {
+        match self {
+            Self::S16le => f32::from(i16::from_le_bytes([bytes[0], bytes[1]])) / 32_768.0,
+            Self::S32le => {
+                i32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]) as f32
+                    / 2_147_483_648.0
+            }
+            Self::F32le => f32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]),
+        }
+    }
+}
+
+/// Describes a headerless stream of interleaved samples.
+#[derive(Debug, Clone, PartialEq, Eq, clap::Args, Deserialize, Serialize)]
+pub struct PcmSpec {
+    /// Sample rate of the incoming stream
+    #[clap(long)]
+    sample_rate: u32,
+
+    /// Number of interleaved channels in the incoming stream
+    #[clap(long, default_value_t = 1)]
+    channels: u16,
+
+    /// Sample encoding of the incoming stream
+    #[clap(long, value_enum, default_value_t)]
+    format: PcmFormat,
+
+    /// Only use these of the stream's channels, numbered from 1 (e.g. `--select-channels 2`)
+    #[clap(long = "select-channels", value_delimiter = ',')]
+    #[serde(default)]
+    selection: Vec<u16>,
+
+    /// How to combine the selected channels into mono
+    #[clap(long, value_enum)]
+    #[serde(default)]
+    downmix: Option<Downmix>,
+}
+
+impl PcmSpec {
+    #[must_use]
+    pub fn new(sample_rate: u32, channels: u16, format: PcmFormat) -> Self {
+        Self {
+            sample_rate,
+            channels,
+            format,
+            selection: Vec::new(),
+            downmix: None,
+        }
+    }
+
+    /// Restricts the stream to these 1-based channels, as [`Session::with_channels`] does for
+    /// a device.
+    ///
+    /// [`Session::with_channels`]: super::recording::Session::with_channels
+    #[must_use]
+    pub fn with_selection(mut self, selection: Vec<u16>) -> Self {
+        self.selection = selection;
+        self
+    }
+
+    #[must_use]
+    pub fn with_downmix(mut self, downmix: Downmix) -> Self {
+        self.downmix = Some(downmix);
+        self
+    }
+
+    pub fn channel_mix(&self) -> Result<ChannelMix, channels::Error> {
+        ChannelMix::new(
+            self.channels,
+            Some(&self.selection),
+            self.downmix.unwrap_or_default(),
+        )
+    }
+
+    #[must_use]
+    pub fn config(&self) -> cpal::StreamConfig {
+        cpal::StreamConfig {
+            channels: self.channels,
+            sample_rate: cpal::SampleRate(self.sample_rate),
+            buffer_size: cpal::BufferSize::Default,
+        }
+    }
+
+    fn frame_bytes(&self) -> usize {
+        self.format.bytes_per_sample() * usize::from(self.channels.max(1))
+    }
+}
+
+/// Decodes raw PCM from a reader into chunks of interleaved `f32` samples.
+///
+/// Short reads (as from a pipe) are buffered until a whole frame is available, so chunks never
+/// split a frame across channels.
+pub struct PcmReader<R: Read> {
+    inner: R,
+    spec: PcmSpec,
+    pending: Vec<u8>,
+}
+
+impl<R: Read> PcmReader<R> {
+    pub fn new(inner: R, spec: PcmSpec) -> Self {
+        Self {
+            inner,
+            spec,
+            pending: Vec::new(),
+        }
+    }
+
+    fn decode_frames(&mut self) -> Vec<f32> {
+        let frame_bytes = self.spec.frame_bytes();
+        let usable = self.pending.len() - self.pending.len() % frame_bytes;
+        let samples = self.pending[..usable]
+            .chunks_exact(self.spec.format.bytes_per_sample())
+            .map(|b| self.spec.format.decode(b))
+            .collect();
+        self.pending.drain(..usable);
+        samples
+    }
+}
+
+impl<R: Read> Iterator for PcmReader<R> {
+    type Item = io::Result<Vec<f32>>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let mut buf = vec![0_u8; CHUNK_FRAMES * self.spec.frame_bytes()];
+        loop {
+            match self.inner.read(&mut buf) {
+                Ok(0) => {
+                    if !self.pending.is_empty() {
+                        log::warn!("Dropping {} bytes of partial frame", self.pending.len());
+                        self.pending.clear();
+                    }
+                    return None;
+                }
+                Ok(n) => {
+                    self.pending.extend_from_slice(&buf[..n]);
+                    if self.pending.len() >= self.spec.frame_bytes() {
+                        return Some(Ok(self.decode_frames()));
+                    }
+                }
+                Err(e) if e.kind() == io::ErrorKind::Interrupted => {}
+                Err(e) => return Some(Err(e)),
+            }
+        }
+    }
+}
+
+fn read_from_pcm<P, R>(mut processor: P, reader: PcmReader<R>) -> Result<(), Error>
+where
+    P: Process<Input = f32>,
+    R: Read,
+{
+    for chunk in reader {
+        processor.write_input_data(&chunk?);
+    }
+    processor.finish();
+    Ok(())
+}
+
+/// Reads the stream to its end, downmixing and resampling it to mono at
+/// [`super::OUTPUT_SAMPLE_RATE`], then running it through `dsp`.
+pub fn resample<R: Read, S: MySample>(
+    reader: R,
+    spec: &PcmSpec,
+    dsp: &DspConfig,
+) -> Result<Vec<S>, Error> {
+    let node = ProcessNode::new(|it| {
+        let (samples, errors) = Captured::collect(it).into_parts();
+        for e in errors {
+            log::error!("Error while reading PCM: {e}");
+        }
+        samples
+    });
+    let (sink, handle) = node.run();
+
+    let mix = spec.channel_mix()?;
+    read_from_pcm(
+        Processor::<f32, S>::new(sink, spec.config(), mix).with_dsp(*dsp),
+        PcmReader::new(reader, spec.clone()),
+    )?;
+
+    handle.join().map_err(|_| Error::Sync)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_reader_holds_partial_frames() {
+        // Two stereo s16le frames, with a dangling byte that never completes a third frame
+        let bytes: Vec<u8> = [0_i16, 16384, -16384, i16::MIN]
+            .iter()
+            .flat_map(|s| s.to_le_bytes())
+            .chain([0x7f])
+            .collect();
+
+        let spec = PcmSpec::new(16_000, 2, PcmFormat::S16le);
+        let chunks: Vec<Vec<f32>> = PcmReader::new(bytes.as_slice(), spec)
+            .collect::<io::Result<_>>()
+            .unwrap();
+
+        assert_eq!(chunks, vec![vec![0.0, 0.5, -0.5, -1.0]]);
+    }
+
+    #[test]
+    fn test_selection_and_downmix() {
+        let frames = [0.1_f32, 0.5, 0.3, -0.5];
+
+        let spec = PcmSpec::new(16_000, 2, PcmFormat::F32le).with_selection(vec![2]);
+        let mut out = Vec::new();
+        spec.channel_mix().unwrap().downmix_into(&frames, &mut out);
+        assert_eq!(out, vec![0.5, -0.5]);
+
+        let spec = PcmSpec::new(16_000, 2, PcmFormat::F32le).with_downmix(Downmix::Strongest);
+        spec.channel_mix().unwrap().downmix_into(&frames, &mut out);
+        assert_eq!(out, vec![0.5, -0.5]);
+
+        let spec = PcmSpec::new(16_000, 2, PcmFormat::F32le).with_selection(vec![3]);
+        assert!(matches!(
+            spec.channel_mix(),
+            Err(channels::Error::OutOfRange(3, 2))
+        ));
+    }
+
+    #[test]
+    fn test_decode_f32() {
+        let bytes = 0.25_f32.to_le_bytes();
+        assert!((PcmFormat::F32le.decode(&bytes) - 0.25).abs() < f32::EPSILON);
+    }
+}
@@ -1,26 +1,38 @@
-use std::{iter::Copied, slice::Iter};
+use std::sync::{
+    atomic::{AtomicBool, Ordering},
+    Arc, Mutex,
+};
 
 use cpal::{traits::DeviceTrait, Device, Stream};
 use crossbeam::channel::{SendError, Sender};
-use dasp::{
-    interpolate::sinc::Sinc,
-    ring_buffer,
-    signal::{interpolate::Converter, FromInterleavedSamplesIterator, FromIterator},
-    Signal,
-};
-use itertools::Itertools;
 
-use super::MySample;
+use super::{
+    channels::ChannelMix,
+    dsp::{Chain, DspConfig},
+    resample::Resampler,
+    MySample, OUTPUT_SAMPLE_RATE,
+};
 
 #[derive(Debug, thiserror::Error)]
 pub enum Error {
     #[error("build stream error: {0}")]
     BuildStream(#[from] cpal::BuildStreamError),
 
+    #[error("stream error: {0}")]
+    Stream(#[from] cpal::StreamError),
+
     #[error("Send error")]
     Send,
 }
 
+impl Error {
+    /// Whether the input device went away, e.g. a USB mic being unplugged.
+    #[must_use]
+    pub fn is_device_lost(&self) -> bool {
+        matches!(self, Self::Stream(cpal::StreamError::DeviceNotAvailable))
+    }
+}
+
 impl<T> From<SendError<T>> for Error {
     fn from(_: SendError<T>) -> Self {
         Self::Send
@@ -28,162 +40,348 @@ impl<T> From<SendError<T>> for Error {
 }
 
 pub trait Process {
-    type Frame: dasp::Frame + Default;
-    type Input: cpal::Sample + dasp::Sample + Default;
+    type Input: InputSample;
     type Output: MySample;
-    type Signal<'a>: Signal<Frame = Self::Frame> + Sized + 'a
-    where
-        Self: 'a;
 
     fn config(&self) -> &cpal::StreamConfig;
 
-    fn send(&self, sample: Self::Output) -> Result<(), Error>;
+    /// Sends one block of output samples.
+    fn send(&self, samples: Vec<Self::Output>) -> Result<(), Error>;
     fn send_error(&self, error: Error) -> Result<(), Error>;
 
-    fn interpolator() -> Sinc<[Self::Frame; 128]>;
+    /// A handle for reporting errors from outside the data callback.
+    fn error_sink(&self) -> Sender<AudioMessage<Self::Output>>;
 
-    fn signal<'a>(&'a mut self, input: &'a [Self::Input]) -> Self::Signal<'a>;
+    /// Downmixes and resamples one callback's worth of input, returning the output samples it
+    /// completes.
+    fn mono_samples(&mut self, input: &[Self::Input]) -> Vec<Self::Output>;
 
-    fn frame_to_sample(frame: Self::Frame) -> Self::Output;
+    /// Returns the output samples still held back once the input has ended.
+    fn flush(&mut self) -> Vec<Self::Output>;
 
-    fn mono_samples<'a>(
-        &'a mut self,
-        input: &'a [Self::Input],
-    ) -> Box<dyn Iterator<Item = Self::Output> + 'a> {
-        Box::new(
-            self.signal(input).until_exhausted().map(move |frame| {
-                <Self::Output as cpal::Sample>::from(&Self::frame_to_sample(frame))
-            }),
-        )
+    fn send_block(&self, samples: Vec<Self::Output>) {
+        if samples.is_empty() {
+            return;
+        }
+        // This runs on the audio thread, where a panic can abort the process
+        if let Err(e) = self.send(samples).or_else(|e| self.send_error(e)) {
+            log::error!("Dropping a block of audio nobody is receiving: {e}");
+        }
     }
 
     fn write_input_data(&mut self, input: &[Self::Input]) {
-        for sample in self.mono_samples(input).collect_vec() {
-            self.send(sample)
-                .or_else(|e| self.send_error(e))
-                .expect("Could not send message to audio thread");
-        }
+        let samples = self.mono_samples(input);
+        self.send_block(samples);
+    }
+
+    fn finish(&mut self) {
+        let samples = self.flush();
+        self.send_block(samples);
     }
 }
 
-pub fn read_from_device<P: Process + Send + Sync + 'static>(
-    mut processor: P,
+/// Sends the output a device stream's processor still holds back. Call it once the stream has
+/// been dropped, so no callback can follow it.
+pub type Flush = Box<dyn FnOnce() + Send>;
+
+pub fn read_from_device<P: Process + Send + 'static>(
+    processor: P,
     device: &Device,
-) -> Result<Stream, Error> {
-    Ok(device.build_input_stream(
-        &processor.config().clone(),
-        move |data, _| processor.write_input_data(data),
-        |err| {
-            log::trace!("an error occurred on stream: {}", err);
+) -> Result<(Stream, Flush), Error> {
+    let errors = processor.error_sink();
+    let config = processor.config().clone();
+    let processor = Arc::new(Mutex::new(processor));
+    let callback_processor = processor.clone();
+    let stream = device.build_input_stream(
+        &config,
+        move |data, _| {
+            if let Ok(mut processor) = callback_processor.lock() {
+                processor.write_input_data(data);
+            }
         },
-    )?)
+        move |err| {
+            log::error!("an error occurred on stream: {}", err);
+            // The receiver is gone once the recording has stopped, and then nobody cares
+            let _ = errors.send(AudioMessage::Error(err.into()));
+        },
+    )?;
+
+    let flush: Flush = Box::new(move || {
+        if let Ok(mut processor) = processor.lock() {
+            processor.finish();
+        }
+    });
+    Ok((stream, flush))
+}
+
+/// Number of frames handed to the processor per call when reading from an in-memory buffer,
+/// roughly matching a typical device callback.
+const BUFFER_CHUNK_FRAMES: usize = 1024;
+
+/// Feeds interleaved samples that are already in memory (e.g. a decoded file) through the
+/// processor in device-callback-sized chunks.
+pub fn read_from_buffer<P: Process>(mut processor: P, input: &[P::Input]) {
+    let chunk_size = BUFFER_CHUNK_FRAMES * usize::from(processor.config().channels.max(1));
+    for chunk in input.chunks(chunk_size) {
+        processor.write_input_data(chunk);
+    }
+    processor.finish();
 }
 
+/// What the processor hands to its sink: each device callback's output as one block, so the
+/// channel sees a few dozen messages a second rather than one per sample.
 pub enum AudioMessage<O>
 where
     O: MySample,
 {
-    Data(O),
+    Data(Vec<O>),
     Error(Error),
 }
 
-pub struct Processor<O, const CHANNELS: usize>
+/// Everything a recording's sink received: the audio, plus any errors reported while it was
+/// being captured.
+#[derive(Debug)]
+pub struct Captured<O: MySample> {
+    audio: Vec<O>,
+    errors: Vec<Error>,
+}
+
+impl<O: MySample> Captured<O> {
+    pub fn collect(messages: impl IntoIterator<Item = AudioMessage<O>>) -> Self {
+        let mut captured = Self {
+            audio: Vec::new(),
+            errors: Vec::new(),
+        };
+        for msg in messages {
+            match msg {
+                AudioMessage::Data(block) => captured.audio.extend(block),
+                AudioMessage::Error(e) => captured.errors.push(e),
+            }
+        }
+        captured
+    }
+
+    #[must_use]
+    pub fn audio(&self) -> &[O] {
+        &self.audio
+    }
+
+    #[must_use]
+    pub fn errors(&self) -> &[Error] {
+        &self.errors
+    }
+
+    #[must_use]
+    pub fn into_parts(self) -> (Vec<O>, Vec<Error>) {
+        (self.audio, self.errors)
+    }
+}
+
+/// Lets a recording's sink drop audio while the recording is paused, without closing the
+/// stream. Errors always get through.
+#[derive(Debug, Clone, Default)]
+pub struct Gate(Arc<AtomicBool>);
+
+impl Gate {
+    pub fn pause(&self) {
+        self.0.store(true, Ordering::Relaxed);
+    }
+
+    pub fn resume(&self) {
+        self.0.store(false, Ordering::Relaxed);
+    }
+
+    #[must_use]
+    pub fn is_paused(&self) -> bool {
+        self.0.load(Ordering::Relaxed)
+    }
+
+    /// Whether `msg` should reach the sink.
+    #[must_use]
+    pub fn admits<O: MySample>(&self, msg: &AudioMessage<O>) -> bool {
+        matches!(msg, AudioMessage::Error(_)) || !self.is_paused()
+    }
+}
+
+/// Device sample types the processor can read from, converted to `f32` before resampling.
+pub trait InputSample: cpal::Sample + dasp::Sample + Default + Send + Sync + 'static {}
+impl<S> InputSample for S where S: cpal::Sample + dasp::Sample + Default + Send + Sync + 'static {}
+
+pub struct Processor<I, O>
 where
+    I: InputSample,
     O: MySample,
 {
     config: cpal::StreamConfig,
     sink: Sender<AudioMessage<O>>,
+    mix: ChannelMix,
+    mono: Vec<f32>,
+    resampler: Resampler,
+    resampled: Vec<f32>,
+    dsp: Chain,
+    phantom: std::marker::PhantomData<I>,
 }
 
-impl<O, const CHANNELS: usize> Processor<O, CHANNELS>
+impl<I, O> Processor<I, O>
 where
+    I: InputSample,
     O: MySample,
 {
-    pub fn new(sink: Sender<AudioMessage<O>>, config: cpal::StreamConfig) -> Self {
-        Self { config, sink }
+    /// # Panics
+    ///
+    /// when the mix was built for a different channel count than the stream's
+    pub fn new(sink: Sender<AudioMessage<O>>, config: cpal::StreamConfig, mix: ChannelMix) -> Self {
+        assert_eq!(mix.width(), usize::from(config.channels));
+        Self {
+            resampler: Resampler::new(config.sample_rate.0, OUTPUT_SAMPLE_RATE),
+            config,
+            sink,
+            mix,
+            mono: Vec::new(),
+            resampled: Vec::new(),
+            dsp: Chain::default(),
+            phantom: std::marker::PhantomData,
+        }
+    }
+
+    /// Runs the resampled audio through the stages `dsp` enables before sending it.
+    #[must_use]
+    pub fn with_dsp(mut self, dsp: DspConfig) -> Self {
+        self.dsp = dsp.chain(OUTPUT_SAMPLE_RATE);
+        self
     }
 }
 
-impl<O: MySample> Process for Processor<O, 1> {
-    type Input = f32;
-    type Output = O;
-    type Frame = Self::Input;
+impl<I, O> Processor<I, O>
+where
+    I: InputSample,
+    O: MySample,
+{
+    fn take_resampled(&mut self) -> Vec<O> {
+        self.dsp.process(&mut self.resampled);
+        self.resampled
+            .drain(..)
+            .map(|s| <O as cpal::Sample>::from(&s))
+            .collect()
+    }
+}
 
-    type Signal<'a> =
-        Converter<FromIterator<Copied<Iter<'a, Self::Frame>>>, Sinc<[Self::Frame; 128]>>;
+impl<I: InputSample, O: MySample> Process for Processor<I, O> {
+    type Input = I;
+    type Output = O;
 
     fn config(&self) -> &cpal::StreamConfig {
         &self.config
     }
 
-    fn interpolator() -> Sinc<[Self::Frame; 128]> {
-        Sinc::new(ring_buffer::Fixed::from([Self::Frame::default(); 128]))
-    }
-
-    fn signal<'a>(&'a mut self, input: &'a [Self::Input]) -> Self::Signal<'a> {
-        let signal = dasp::signal::from_iter(input.iter().copied());
-
-        signal.from_hz_to_hz(
-            Self::interpolator(),
-            f64::from(self.config.sample_rate.0),
-            16_000.0,
-        )
+    fn mono_samples(&mut self, input: &[I]) -> Vec<O> {
+        self.mix.downmix_into(input, &mut self.mono);
+        self.resampler.process(&self.mono, &mut self.resampled);
+        self.take_resampled()
     }
 
-    #[inline]
-    fn frame_to_sample(frame: Self::Frame) -> Self::Output {
-        <Self::Output as cpal::Sample>::from(&frame)
+    fn flush(&mut self) -> Vec<O> {
+        self.resampler.finish(&mut self.resampled);
+        let mut samples = self.take_resampled();
+        samples.extend(self.dsp.finish().iter().map(<O as cpal::Sample>::from));
+        samples
     }
 
-    fn send(&self, sample: O) -> Result<(), Error> {
-        Ok(self.sink.send(AudioMessage::Data(sample))?)
+    fn send(&self, samples: Vec<O>) -> Result<(), Error> {
+        Ok(self.sink.send(AudioMessage::Data(samples))?)
     }
 
     fn send_error(&self, error: Error) -> Result<(), Error> {
         Ok(self.sink.send(AudioMessage::Error(error))?)
     }
-}
-
-impl<O: MySample> Process for Processor<O, 2> {
-    type Input = f32;
-    type Output = O;
-    type Frame = [f32; 2];
-
-    type Signal<'a> = Converter<
-        FromInterleavedSamplesIterator<Copied<Iter<'a, Self::Input>>, Self::Frame>,
-        Sinc<[Self::Frame; 128]>,
-    >;
 
-    fn config(&self) -> &cpal::StreamConfig {
-        &self.config
-    }
-
-    fn interpolator() -> Sinc<[Self::Frame; 128]> {
-        Sinc::new(ring_buffer::Fixed::from([Self::Frame::default(); 128]))
+    fn error_sink(&self) -> Sender<AudioMessage<O>> {
+        self.sink.clone()
     }
+}
 
-    fn signal<'a>(&'a mut self, input: &'a [Self::Input]) -> Self::Signal<'a> {
-        let signal =
-            dasp::signal::from_interleaved_samples_iter::<_, [f32; 2]>(input.iter().copied());
-
-        signal.from_hz_to_hz(
-            Self::interpolator(),
-            f64::from(self.config.sample_rate.0),
-            16_000.0,
-        )
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::audio::channels::Downmix;
+
+    #[test]
+    fn test_buffer_sends_blocks() {
+        let config = cpal::StreamConfig {
+            channels: 1,
+            sample_rate: cpal::SampleRate(48_000),
+            buffer_size: cpal::BufferSize::Default,
+        };
+        let mix = ChannelMix::new(1, None, Downmix::Average).unwrap();
+        let (sink, source) = crossbeam::channel::unbounded();
+
+        let input = vec![0.25_f32; 48_000];
+        read_from_buffer(Processor::<f32, f32>::new(sink, config, mix), &input);
+
+        let blocks: Vec<Vec<f32>> = source
+            .iter()
+            .map(|msg| match msg {
+                AudioMessage::Data(block) => block,
+                AudioMessage::Error(e) => panic!("{e}"),
+            })
+            .collect();
+
+        // One block per chunk, plus the resampler's tail
+        assert_eq!(blocks.len(), input.len().div_ceil(BUFFER_CHUNK_FRAMES) + 1);
+        assert_eq!(blocks.iter().map(Vec::len).sum::<usize>(), 16_000);
     }
 
-    #[inline]
-    fn frame_to_sample(frame: Self::Frame) -> Self::Output {
-        let avg = (frame[0] + frame[1]) / 2.0;
-        <Self::Output as cpal::Sample>::from(&avg)
+    #[test]
+    fn test_closed_sink_drops_audio() {
+        let config = cpal::StreamConfig {
+            channels: 1,
+            sample_rate: cpal::SampleRate(16_000),
+            buffer_size: cpal::BufferSize::Default,
+        };
+        let mix = ChannelMix::new(1, None, Downmix::Average).unwrap();
+        let (sink, source) = crossbeam::channel::unbounded();
+        drop(source);
+
+        // Must not panic, as it would inside a device callback
+        read_from_buffer(
+            Processor::<f32, f32>::new(sink, config, mix),
+            &[0.25; 4_096],
+        );
     }
 
-    fn send(&self, sample: O) -> Result<(), Error> {
-        Ok(self.sink.send(AudioMessage::Data(sample))?)
+    #[test]
+    fn test_captured_keeps_audio_and_errors() {
+        let captured = Captured::collect([
+            AudioMessage::Data(vec![0.1_f32, 0.2]),
+            AudioMessage::Error(cpal::StreamError::DeviceNotAvailable.into()),
+            AudioMessage::Data(vec![0.3]),
+        ]);
+
+        assert_eq!(captured.audio(), &[0.1, 0.2, 0.3]);
+        assert_eq!(captured.errors().len(), 1);
+        assert!(captured.errors()[0].is_device_lost());
     }
 
-    fn send_error(&self, error: Error) -> Result<(), Error> {
-        Ok(self.sink.send(AudioMessage::Error(error))?)
+    #[test]
+    fn test_paused_gate_drops_audio_only() {
+        let gate = Gate::default();
+        let (sink, source) = crossbeam::channel::unbounded();
+        let send = |msg: AudioMessage<f32>| {
+            if gate.admits(&msg) {
+                sink.send(msg).unwrap();
+            }
+        };
+
+        send(AudioMessage::Data(vec![0.1; 4]));
+        gate.pause();
+        send(AudioMessage::Data(vec![0.2; 4]));
+        send(AudioMessage::Error(Error::Send));
+        gate.resume();
+        send(AudioMessage::Data(vec![0.3; 4]));
+        drop(sink);
+
+        let captured = Captured::collect(source);
+        assert_eq!(captured.audio(), [[0.1; 4], [0.3; 4]].concat());
+        assert_eq!(captured.errors().len(), 1);
     }
 }
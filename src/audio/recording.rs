@@ -1,34 +1,43 @@
 use std::{
     fmt::{Debug, Display},
-    sync::Arc,
+    path::{Path, PathBuf},
+    sync::{Arc, Mutex},
     thread,
+    time::{Duration, SystemTime},
 };
 
 use cpal::{
     traits::{DeviceTrait, HostTrait, StreamTrait},
     StreamConfig,
 };
+use crossbeam::channel::Sender;
 use itertools::Itertools;
 use serde::{Deserialize, Serialize};
 
 use super::{
-    controller::Controller,
-    process::{self, read_from_device, Processor},
-    MySample,
+    channels::{self, ChannelMix, Downmix},
+    controller::{Controller, WaitError},
+    devices::{self, DeviceMatch, Selector},
+    dsp::DspConfig,
+    endpoint::Endpointing,
+    file::{self, AudioFile},
+    monitor::Tap,
+    process::{self, read_from_buffer, read_from_device, AudioMessage, Flush, Processor},
+    vad, MySample, OUTPUT_SAMPLE_RATE,
 };
 use crate::{audio::controller::RecordState, whisper::transcription::Model};
 
+/// How long opening the input may take before a start is reported as failed.
+const START_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// How long the recording thread may take to close its input once stopped.
+const STOP_TIMEOUT: Duration = Duration::from_secs(10);
+
 #[derive(Debug, thiserror::Error)]
 pub enum Error {
     #[error("no supported configs found")]
     NoSupportedConfigs,
 
-    #[error("unsupported channel count: {0}")]
-    InvalidChannelCount(u16),
-
-    #[error("invalid sample format: {0:?}")]
-    InvalidSampleFormat(cpal::SampleFormat),
-
     #[error("play stream error: {0}")]
     PlayStream(#[from] cpal::PlayStreamError),
 
@@ -38,9 +47,24 @@ pub enum Error {
     #[error("process error: {0}")]
     Process(#[from] process::Error),
 
+    #[error("audio file error: {0}")]
+    File(#[from] file::Error),
+
+    #[error("channel selection error: {0}")]
+    Channels(#[from] channels::Error),
+
     #[error("failed to join recording thread")]
     Sync,
 
+    #[error("recording failed to start: {0}")]
+    StartFailed(String),
+
+    #[error("recording didn't {0} within {1:?}")]
+    Timeout(&'static str, Duration),
+
+    #[error("recording was stopped before it started")]
+    NotStarted,
+
     #[error("try to eliminate this")]
     ThreadPanic(String),
 }
@@ -51,7 +75,7 @@ where
     RS: Send,
     RE: Send,
 {
-    handle: thread::JoinHandle<Result<StreamConfig, RE>>,
+    handle: thread::JoinHandle<Result<Metadata, RE>>,
     controller: Controller,
     phantom: std::marker::PhantomData<S>,
     receiving_handle: thread::JoinHandle<RS>,
@@ -60,25 +84,41 @@ where
 impl<S, RS> Recording<S, RS, Error>
 where
     S: MySample,
-    RS: Send + Into<Vec<f32>> + 'static,
+    RS: Send + 'static,
 {
+    /// Signals the recording thread to start and waits until it has, failing if the thread
+    /// reports an error (e.g. the device couldn't be opened) or takes longer than
+    /// [`START_TIMEOUT`]. Either way the recording should still be stopped afterwards.
     #[tracing::instrument(skip(self))]
-    pub fn start(&self) {
+    pub fn start(&self) -> Result<(), Error> {
         self.controller.start();
-        self.controller.wait_for(RecordState::Recording);
-        log::info!("Recording started");
+        match self.controller.wait_for_recording(START_TIMEOUT) {
+            Ok(()) => {
+                log::info!("Recording started");
+                Ok(())
+            }
+            Err(WaitError::Failed(reason)) => Err(Error::StartFailed(reason)),
+            Err(WaitError::Timeout) => Err(Error::Timeout("start", START_TIMEOUT)),
+        }
     }
 
-    pub fn stop(self) -> Result<(StreamConfig, Vec<f32>), Error> {
+    /// Stops the recording and collects its audio. If the recording thread doesn't exit within
+    /// [`STOP_TIMEOUT`] it's abandoned rather than joined, so a hung device can't hang the
+    /// caller too.
+    pub fn stop(self) -> Result<(Metadata, RS), Error> {
         self.controller.stop();
-        // The handle's thread returns Result<StreamConfig, Error>
-        let metadata_result = Self::join_handle(self.handle)?; // Result<Result<StreamConfig, Error>, Error> -> Result<StreamConfig, Error>
+        self.controller
+            .wait_for_exit(STOP_TIMEOUT)
+            .map_err(|_| Error::Timeout("stop", STOP_TIMEOUT))?;
+
+        // The handle's thread returns Result<Metadata, Error>
+        let metadata_result = Self::join_handle(self.handle)?; // Result<Result<Metadata, Error>, Error> -> Result<Metadata, Error>
         let metadata = metadata_result?; // Handle the inner Result
 
         // The receiving_handle's thread returns RS
         let audio = Self::join_handle_rs(self.receiving_handle)?;
 
-        Ok((metadata, audio.into()))
+        Ok((metadata, audio))
     }
 
     // This function joins a thread handle that returns Result<T, Error_Returned_By_Thread>
@@ -132,6 +172,7 @@ where
     pub fn controlled(
         session: Session,
         node: crate::sync::ProcessNode<process::AudioMessage<S>, RS>,
+        monitor: Option<Tap>,
     ) -> Result<Recording<S, RS, Error>, Error>
     where
         S: MySample,
@@ -141,39 +182,21 @@ where
 
         let c2 = controller.clone();
 
-        let (sink_send, sink_handle) = node.run();
-
-        let handle = thread::spawn(move || -> Result<StreamConfig, Error> {
-            // Explicit return type
-            {
-                let (device, supported_config) = session
-                    .supported_configs()?
-                    .next()
-                    .ok_or(Error::NoSupportedConfigs)?;
-
-                c2.wait_for(RecordState::Started);
-
-                let cpal::SampleFormat::F32 = supported_config.sample_format() else {
-                    return Err(Error::InvalidSampleFormat(supported_config.sample_format()));
-                };
+        // Decode up front so a bad path is reported to the caller rather than inside the thread.
+        let input_file = match session.input_file() {
+            Some(path) => Some((path.display().to_string(), AudioFile::open(path)?)),
+            None => None,
+        };
 
-                let cfg: cpal::StreamConfig = supported_config.clone().into();
-                let stream = if cfg.channels == 1 {
-                    let resampler = Processor::<S, 1>::new(sink_send, cfg.clone());
-                    read_from_device(resampler, &device).map_err(Error::from)
-                } else if cfg.channels == 2 {
-                    let resampler = Processor::<S, 2>::new(sink_send, cfg.clone());
-                    read_from_device(resampler, &device).map_err(Error::from)
-                } else {
-                    Err(Error::InvalidChannelCount(cfg.channels))
-                }?;
-
-                stream.play()?;
-                c2.recording();
+        let (sink_send, sink_handle) = node.run();
 
-                c2.wait_for(RecordState::Stopped);
-                Ok(cfg)
-            } // .map_err is removed here, error is handled by ?
+        let handle = thread::spawn(move || -> Result<Metadata, Error> {
+            let _exit = c2.exit_guard();
+            let result = record(&c2, &session, input_file, sink_send, monitor);
+            if let Err(ref e) = result {
+                c2.fail(e.to_string());
+            }
+            result
         });
 
         Ok(Recording {
@@ -185,6 +208,419 @@ where
     }
 }
 
+/// Body of the recording thread: opens the session's input, feeds it to `sink_send` between
+/// the controller's start and stop, and reports where the audio came from.
+fn record<S: MySample>(
+    c: &Controller,
+    session: &Session,
+    input_file: Option<(String, AudioFile)>,
+    sink_send: Sender<AudioMessage<S>>,
+    monitor: Option<Tap>,
+) -> Result<Metadata, Error> {
+    if let Some((path, file)) = input_file {
+        if !c.wait_for_start() {
+            return Err(Error::NotStarted);
+        }
+
+        let cfg = file.config().clone();
+        let mix = session.channel_mix(cfg.channels)?;
+        c.recording();
+        let started = SystemTime::now();
+        read_from_buffer(
+            Processor::<f32, S>::new(sink_send, cfg.clone(), mix)
+                .with_dsp(session.dsp().unwrap_or_default()),
+            file.samples(),
+        );
+
+        c.wait_for(&RecordState::Stopped);
+        return Ok(Metadata::new(path, cfg).with_times(started, SystemTime::now()));
+    }
+
+    if let Some(tap) = monitor.filter(|m| m.serves(session)) {
+        if !c.wait_for_start() {
+            return Err(Error::NotStarted);
+        }
+
+        let dsp = Arc::new(Mutex::new(
+            session.dsp().unwrap_or_default().chain(OUTPUT_SAMPLE_RATE),
+        ));
+        let (subscriber_dsp, subscriber_sink) = (dsp.clone(), sink_send.clone());
+        tap.subscribe(move |msg| {
+            let msg = match msg {
+                AudioMessage::Data(mut block) => {
+                    subscriber_dsp.lock().unwrap().process(&mut block);
+                    AudioMessage::Data(block.iter().map(<S as cpal::Sample>::from).collect())
+                }
+                AudioMessage::Error(e) => AudioMessage::Error(e),
+            };
+            subscriber_sink.send(msg).is_ok()
+        });
+
+        c.recording();
+        let started = SystemTime::now();
+        c.wait_for(&RecordState::Stopped);
+        tap.unsubscribe();
+        // The audio still held back by the processing
+        let tail = dsp.lock().unwrap().finish();
+        if !tail.is_empty() {
+            let _ = sink_send.send(AudioMessage::Data(
+                tail.iter().map(<S as cpal::Sample>::from).collect(),
+            ));
+        }
+        return Ok(tap
+            .metadata()
+            .clone()
+            .with_times(started, SystemTime::now()));
+    }
+
+    let (device, supported_config) = session.preferred_config()?;
+
+    if !c.wait_for_start() {
+        return Err(Error::NotStarted);
+    }
+
+    let device_name = device
+        .name()
+        .unwrap_or_else(|_| "Unnamed device".to_string());
+    let cfg: cpal::StreamConfig = supported_config.clone().into();
+    log::debug!(
+        "Opening {device_name} with {:?} samples: {cfg:?}",
+        supported_config.sample_format()
+    );
+    let mix = session.channel_mix(cfg.channels)?;
+    let (stream, flush) = open_stream(
+        &device,
+        &supported_config,
+        mix,
+        session.dsp().unwrap_or_default(),
+        sink_send,
+    )?;
+
+    stream.play()?;
+    c.recording();
+    let started = SystemTime::now();
+
+    c.wait_for(&RecordState::Stopped);
+    drop(stream);
+    // The audio still held back by the resampler and processing
+    flush();
+    Ok(Metadata::new(device_name, cfg)
+        .with_sample_format(supported_config.sample_format())
+        .with_times(started, SystemTime::now()))
+}
+
+/// Builds an input stream reading the device's native sample format, along with what flushes
+/// its processing once it's dropped.
+pub(super) fn open_stream<S: MySample>(
+    device: &cpal::Device,
+    supported_config: &cpal::SupportedStreamConfig,
+    mix: ChannelMix,
+    dsp: DspConfig,
+    sink: Sender<AudioMessage<S>>,
+) -> Result<(cpal::Stream, Flush), Error> {
+    let cfg: StreamConfig = supported_config.clone().into();
+    Ok(match supported_config.sample_format() {
+        cpal::SampleFormat::F32 => read_from_device(
+            Processor::<f32, S>::new(sink, cfg, mix).with_dsp(dsp),
+            device,
+        ),
+        cpal::SampleFormat::I16 => read_from_device(
+            Processor::<i16, S>::new(sink, cfg, mix).with_dsp(dsp),
+            device,
+        ),
+        cpal::SampleFormat::U16 => read_from_device(
+            Processor::<u16, S>::new(sink, cfg, mix).with_dsp(dsp),
+            device,
+        ),
+    }?)
+}
+
+/// Lower is better. Integer formats are converted losslessly, so they're only a fallback in
+/// the sense that float input saves a conversion.
+fn format_rank(format: cpal::SampleFormat) -> u8 {
+    match format {
+        cpal::SampleFormat::F32 => 0,
+        cpal::SampleFormat::I16 => 1,
+        cpal::SampleFormat::U16 => 2,
+    }
+}
+
+/// How a config's sample rate relates to what the session asked for, best first.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+enum RateMatch {
+    /// The rate the session asked for
+    Requested,
+    /// The device's default rate, which the driver doesn't have to convert to
+    Native,
+    /// A whole multiple of the output rate, which resamples cleanly
+    Multiple,
+    /// Some other rate above the output rate
+    Higher,
+    /// Below the output rate, so detail whisper could use is lost
+    Lower,
+}
+
+/// How well a device config suits a session; better configs compare lower.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+struct ConfigRank {
+    rate: RateMatch,
+    /// Fewer channels mean less to read and downmix
+    channels: u16,
+    format: u8,
+}
+
+/// What a session asks of a device, used to rank the config ranges the device offers.
+#[derive(Debug, Clone, Copy)]
+struct ConfigRequest {
+    sample_rate: Option<u32>,
+    native_rate: Option<u32>,
+    min_channels: u16,
+}
+
+impl ConfigRequest {
+    /// The rate to open `range` at and how well it then suits the request, or `None` when it
+    /// can't serve the request at all.
+    fn rank(&self, range: &cpal::SupportedStreamConfigRange) -> Option<(u32, ConfigRank)> {
+        if range.channels() < self.min_channels {
+            return None;
+        }
+
+        let (min, max) = (range.min_sample_rate().0, range.max_sample_rate().0);
+        let in_range = |rate: &u32| (min..=max).contains(rate);
+        let (rate, rate_match) = if let Some(rate) = self.sample_rate {
+            (Some(rate).filter(in_range)?, RateMatch::Requested)
+        } else if let Some(rate) = self.native_rate.filter(in_range) {
+            (rate, RateMatch::Native)
+        } else if let Some(rate) = (1..=max / OUTPUT_SAMPLE_RATE)
+            .map(|n| n * OUTPUT_SAMPLE_RATE)
+            .find(in_range)
+        {
+            (rate, RateMatch::Multiple)
+        } else if min > OUTPUT_SAMPLE_RATE {
+            (min, RateMatch::Higher)
+        } else {
+            (max, RateMatch::Lower)
+        };
+
+        Some((
+            rate,
+            ConfigRank {
+                rate: rate_match,
+                channels: range.channels(),
+                format: format_rank(range.sample_format()),
+            },
+        ))
+    }
+}
+
+/// Describes where a finished recording's audio came from, what it was turned into, and when.
+#[derive(Debug, Clone)]
+pub struct Metadata {
+    device_name: String,
+    config: StreamConfig,
+    sample_format: Option<cpal::SampleFormat>,
+    output_rate: u32,
+    started: Option<SystemTime>,
+    stopped: Option<SystemTime>,
+}
+
+impl Metadata {
+    #[must_use]
+    pub fn new(device_name: String, config: StreamConfig) -> Self {
+        Self {
+            device_name,
+            config,
+            sample_format: None,
+            output_rate: OUTPUT_SAMPLE_RATE,
+            started: None,
+            stopped: None,
+        }
+    }
+
+    /// Records when the audio started being captured and when capture stopped.
+    #[must_use]
+    pub fn with_times(mut self, started: SystemTime, stopped: SystemTime) -> Self {
+        self.started = Some(started);
+        self.stopped = Some(stopped);
+        self
+    }
+
+    /// Records the sample format the device was opened with.
+    #[must_use]
+    pub fn with_sample_format(mut self, format: cpal::SampleFormat) -> Self {
+        self.sample_format = Some(format);
+        self
+    }
+
+    /// Name of the input device, or the path of the file the audio was read from.
+    #[must_use]
+    pub fn device_name(&self) -> &str {
+        &self.device_name
+    }
+
+    /// The stream configuration the audio was captured with, before resampling.
+    #[must_use]
+    pub fn config(&self) -> &StreamConfig {
+        &self.config
+    }
+
+    /// The device's sample format, `None` when the audio was read from a file.
+    #[must_use]
+    pub fn sample_format(&self) -> Option<cpal::SampleFormat> {
+        self.sample_format
+    }
+
+    /// The rate the audio was resampled to, which is what it's transcribed at.
+    #[must_use]
+    pub fn output_rate(&self) -> u32 {
+        self.output_rate
+    }
+
+    /// How long `samples` of the resampled audio last.
+    #[must_use]
+    pub fn duration(&self, samples: usize) -> Duration {
+        let rate = u64::from(self.output_rate);
+        let samples = samples as u64;
+        Duration::from_secs(samples / rate)
+            + Duration::from_nanos(samples % rate * 1_000_000_000 / rate)
+    }
+
+    #[must_use]
+    pub fn input_info(&self) -> InputInfo {
+        InputInfo {
+            device: self.device_name.clone(),
+            stream: StreamInfo::from(&self.config),
+            sample_format: self.sample_format.map(|f| format!("{f:?}").to_lowercase()),
+        }
+    }
+
+    /// The report on a recording that produced `samples` of resampled audio.
+    #[must_use]
+    pub fn report(&self, samples: usize) -> RecordingInfo {
+        RecordingInfo {
+            input: self.input_info(),
+            output_rate: self.output_rate,
+            samples,
+            duration_ms: millis(self.duration(samples)),
+            started_at: self.started.map(epoch_millis),
+            stopped_at: self.stopped.map(epoch_millis),
+        }
+    }
+}
+
+fn millis(duration: Duration) -> u64 {
+    u64::try_from(duration.as_millis()).unwrap_or(u64::MAX)
+}
+
+/// Milliseconds since the Unix epoch, or 0 for times before it.
+fn epoch_millis(time: SystemTime) -> u64 {
+    time.duration_since(SystemTime::UNIX_EPOCH)
+        .map_or(0, millis)
+}
+
+/// A finished recording's metadata, as reported to clients.
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize, Serialize)]
+pub struct RecordingInfo {
+    input: InputInfo,
+    /// Rate of the audio that was transcribed
+    output_rate: u32,
+    samples: usize,
+    duration_ms: u64,
+    /// Milliseconds since the Unix epoch
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    started_at: Option<u64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    stopped_at: Option<u64>,
+}
+
+impl RecordingInfo {
+    #[must_use]
+    pub fn input(&self) -> &InputInfo {
+        &self.input
+    }
+
+    #[must_use]
+    pub fn output_rate(&self) -> u32 {
+        self.output_rate
+    }
+
+    #[must_use]
+    pub fn samples(&self) -> usize {
+        self.samples
+    }
+
+    #[must_use]
+    pub fn duration(&self) -> Duration {
+        Duration::from_millis(self.duration_ms)
+    }
+
+    #[must_use]
+    pub fn started_at(&self) -> Option<SystemTime> {
+        self.started_at
+            .map(|ms| SystemTime::UNIX_EPOCH + Duration::from_millis(ms))
+    }
+
+    #[must_use]
+    pub fn stopped_at(&self) -> Option<SystemTime> {
+        self.stopped_at
+            .map(|ms| SystemTime::UNIX_EPOCH + Duration::from_millis(ms))
+    }
+}
+
+impl Display for RecordingInfo {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{:.1?} from {}", self.duration(), self.input)
+    }
+}
+
+/// The input a recording was captured from, as reported to clients.
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize, Serialize)]
+pub struct InputInfo {
+    device: String,
+    #[serde(flatten)]
+    stream: StreamInfo,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    sample_format: Option<String>,
+}
+
+impl InputInfo {
+    #[must_use]
+    pub fn device(&self) -> &str {
+        &self.device
+    }
+}
+
+impl Display for InputInfo {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}: {}ch", self.device, self.stream.channels)?;
+        if let Some(ref format) = self.sample_format {
+            write!(f, " {format}")?;
+        }
+        write!(f, " {} Hz", self.stream.sample_rate)
+    }
+}
+
+/// Serializable view of a [`StreamConfig`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, Serialize)]
+pub struct StreamInfo {
+    channels: u16,
+    sample_rate: u32,
+    buffer_size: Option<u32>,
+}
+
+impl From<&StreamConfig> for StreamInfo {
+    fn from(config: &StreamConfig) -> Self {
+        Self {
+            channels: config.channels,
+            sample_rate: config.sample_rate.0,
+            buffer_size: match config.buffer_size {
+                cpal::BufferSize::Default => None,
+                cpal::BufferSize::Fixed(n) => Some(n),
+            },
+        }
+    }
+}
+
 #[derive(Debug, thiserror::Error)]
 pub enum SessionError {
     #[error("audio device error: {0}")]
@@ -192,6 +628,9 @@ pub enum SessionError {
 
     #[error("recording session parameters error: {0}")]
     Parameters(String),
+
+    #[error("device selection error: {0}")]
+    Selector(#[from] devices::Error),
 }
 
 #[derive(Debug, Default, Clone, PartialEq, Eq, Deserialize, Serialize)]
@@ -200,6 +639,17 @@ pub struct Session {
     sample_rate: Option<u32>,
     prompt: Option<String>,
     model: Option<Model>,
+    input_file: Option<PathBuf>,
+    channels: Option<Vec<u16>>,
+    downmix: Option<Downmix>,
+    device_match: Option<DeviceMatch>,
+    max_secs: Option<u32>,
+    idle_secs: Option<u32>,
+    dsp: Option<DspConfig>,
+    host: Option<String>,
+    endpointing: Option<Endpointing>,
+    continuous: Option<Endpointing>,
+    vad: Option<vad::Tuning>,
 }
 
 impl Session {
@@ -215,14 +665,114 @@ impl Session {
             sample_rate,
             prompt,
             model,
+            input_file: None,
+            channels: None,
+            downmix: None,
+            device_match: None,
+            max_secs: None,
+            idle_secs: None,
+            dsp: None,
+            host: None,
+            endpointing: None,
+            continuous: None,
+            vad: None,
         }
     }
 
+    /// Reads audio from a WAV or FLAC file instead of an input device.
+    #[must_use]
+    pub fn with_input_file(mut self, path: impl Into<PathBuf>) -> Self {
+        self.input_file = Some(path.into());
+        self
+    }
+
+    /// Restricts the input to these 1-based channels, e.g. `[3]` for a mic on input 3 or
+    /// `[1, 2]` to mix a stereo pair.
+    #[must_use]
+    pub fn with_channels(mut self, channels: Vec<u16>) -> Self {
+        self.channels = Some(channels);
+        self
+    }
+
+    #[must_use]
+    pub fn with_downmix(mut self, downmix: Downmix) -> Self {
+        self.downmix = Some(downmix);
+        self
+    }
+
+    /// Changes how the input device name is interpreted, e.g. as an index or a regex.
+    #[must_use]
+    pub fn with_device_match(mut self, device_match: DeviceMatch) -> Self {
+        self.device_match = Some(device_match);
+        self
+    }
+
+    /// Stops the recording on its own after this many seconds.
+    #[must_use]
+    pub fn with_max_secs(mut self, secs: u32) -> Self {
+        self.max_secs = Some(secs);
+        self
+    }
+
+    /// Stops the recording on its own once no speech has been heard for this many seconds.
+    #[must_use]
+    pub fn with_idle_secs(mut self, secs: u32) -> Self {
+        self.idle_secs = Some(secs);
+        self
+    }
+
+    /// Processes the audio with these stages instead of the daemon's defaults.
+    #[must_use]
+    pub fn with_dsp(mut self, dsp: DspConfig) -> Self {
+        self.dsp = Some(dsp);
+        self
+    }
+
+    /// Stops the recording on its own once the speaker has finished, for hands-free use.
+    #[must_use]
+    pub fn with_endpointing(mut self, endpointing: Endpointing) -> Self {
+        self.endpointing = Some(endpointing);
+        self
+    }
+
+    /// Keeps recording until stopped, transcribing each utterance on its own as soon as the
+    /// speaker finishes it, going by `segmenting`.
+    #[must_use]
+    pub fn with_continuous(mut self, segmenting: Endpointing) -> Self {
+        self.continuous = Some(segmenting);
+        self
+    }
+
+    /// Adjusts how speech is told apart from silence. The mode, frame length and energy
+    /// threshold apply everywhere speech is detected; onset and release only to trimming and
+    /// the wake phrase, as endpointing has its own minimum speech and hangover.
+    #[must_use]
+    pub fn with_vad(mut self, vad: vad::Tuning) -> Self {
+        self.vad = Some(vad);
+        self
+    }
+
+    /// Records through this audio host, e.g. "jack", instead of the default one.
+    #[must_use]
+    pub fn with_host(mut self, host: String) -> Self {
+        self.host = Some(host);
+        self
+    }
+
     #[must_use]
     pub fn device_name(&self) -> Option<&str> {
         self.input_device.as_deref()
     }
 
+    #[must_use]
+    pub fn device_match(&self) -> DeviceMatch {
+        self.device_match.unwrap_or_default()
+    }
+
+    pub fn device_selector(&self) -> Result<Selector, devices::Error> {
+        Selector::new(self.device_name(), self.device_match())
+    }
+
     #[must_use]
     pub fn sample_rate(&self) -> Option<u32> {
         self.sample_rate
@@ -233,28 +783,124 @@ impl Session {
         self.model
     }
 
+    #[must_use]
+    pub fn input_file(&self) -> Option<&Path> {
+        self.input_file.as_deref()
+    }
+
+    #[must_use]
+    pub fn channels(&self) -> Option<&[u16]> {
+        self.channels.as_deref()
+    }
+
+    #[must_use]
+    pub fn max_duration(&self) -> Option<Duration> {
+        self.max_secs.map(|secs| Duration::from_secs(secs.into()))
+    }
+
+    #[must_use]
+    pub fn idle_timeout(&self) -> Option<Duration> {
+        self.idle_secs.map(|secs| Duration::from_secs(secs.into()))
+    }
+
+    #[must_use]
+    pub fn dsp(&self) -> Option<DspConfig> {
+        self.dsp
+    }
+
+    #[must_use]
+    pub fn endpointing(&self) -> Option<Endpointing> {
+        self.endpointing
+    }
+
+    /// Whether a recording of this session can end without a stop command.
+    #[must_use]
+    pub fn stops_itself(&self) -> bool {
+        self.max_secs.is_some() || self.idle_secs.is_some() || self.endpointing.is_some()
+    }
+
+    /// How utterances are cut from a continuous recording, if this is one.
+    #[must_use]
+    pub fn continuous(&self) -> Option<Endpointing> {
+        self.continuous
+    }
+
+    #[must_use]
+    pub fn vad(&self) -> Option<vad::Tuning> {
+        self.vad
+    }
+
+    #[must_use]
+    pub fn host(&self) -> Option<&str> {
+        self.host.as_deref()
+    }
+
+    #[must_use]
+    pub fn downmix(&self) -> Downmix {
+        self.downmix.unwrap_or_default()
+    }
+
+    pub fn channel_mix(&self, channels: u16) -> Result<ChannelMix, channels::Error> {
+        ChannelMix::new(channels, self.channels(), self.downmix())
+    }
+
+    /// Every config of the matching devices that can serve the session, each opened at the
+    /// rate that suits the session best, in device order.
     pub fn supported_configs(
         &self,
     ) -> Result<
         impl Iterator<Item = (Arc<cpal::Device>, cpal::SupportedStreamConfig)> + '_,
         SessionError,
     > {
-        let devices = cpal::default_host()
+        Ok(self.ranked_configs()?.map(|(d, c, _)| (d, c)))
+    }
+
+    /// The best ranked config of the first device, keeping the earliest of equally good ones.
+    fn first_device_best<D, C>(
+        configs: impl IntoIterator<Item = (Arc<D>, C, ConfigRank)>,
+    ) -> Option<(Arc<D>, C, ConfigRank)> {
+        let mut best: Option<(Arc<D>, C, ConfigRank)> = None;
+
+        for (device, config, rank) in configs {
+            match best {
+                Some((ref d, ..)) if !Arc::ptr_eq(d, &device) => break,
+                Some((_, _, ref r)) if *r <= rank => {}
+                _ => best = Some((device, config, rank)),
+            }
+        }
+
+        best
+    }
+
+    fn ranked_configs(
+        &self,
+    ) -> Result<
+        impl Iterator<Item = (Arc<cpal::Device>, cpal::SupportedStreamConfig, ConfigRank)> + '_,
+        SessionError,
+    > {
+        let selector = self.device_selector()?;
+        let devices = devices::host(self.host())?
             .input_devices()?
-            .filter_map(|x| {
+            .enumerate()
+            .filter_map(|(i, x)| {
                 let name = x.name().ok()?;
-                let Some(pat) = self.device_name() else {
-                    return Some(x);
-                };
-                name.contains(pat).then_some(x)
+                selector.matches(i, &name).then_some(x)
             })
             .map(Arc::new)
             .collect_vec();
+        let min_channels = ChannelMix::required_channels(self.channels());
 
         let device_config_pairs = devices
             .into_iter()
-            .map(|d| match d.supported_input_configs() {
-                Ok(cfgs) => Ok(cfgs.map(move |cfg| (d.clone(), cfg))),
+            .map(move |d| match d.supported_input_configs() {
+                Ok(cfgs) => {
+                    let request = ConfigRequest {
+                        sample_rate: self.sample_rate(),
+                        native_rate: d.default_input_config().ok().map(|c| c.sample_rate().0),
+                        min_channels,
+                    };
+                    Ok(cfgs.map(move |cfg| (d.clone(), request, cfg)))
+                }
                 Err(e) => {
                     log::error!("Error getting supported input configs: {}", e);
                     Err(e)
@@ -263,19 +909,11 @@ impl Session {
             .flatten_ok();
 
         Ok(device_config_pairs.filter_map(|r| {
-            let Ok((d, c)) = r else {
+            let Ok((d, request, range)) = r else {
                 return None;
             };
-            let sample_rate = self.sample_rate().map_or_else(
-                || c.min_sample_rate().max(cpal::SampleRate(16000)),
-                cpal::SampleRate,
-            );
-            if c.min_sample_rate() > sample_rate || c.max_sample_rate() < sample_rate {
-                None
-            } else {
-                let min_sample_rate = c.min_sample_rate();
-                Some((d, c.with_sample_rate(min_sample_rate)))
-            }
+            let (rate, rank) = request.rank(&range)?;
+            Some((d, range.with_sample_rate(cpal::SampleRate(rate)), rank))
         }))
     }
 
@@ -283,4 +921,184 @@ impl Session {
     pub fn prompt(&self) -> Option<&str> {
         self.prompt.as_deref()
     }
+
+    /// Negotiates the config to record with: of the first matching device's configs, the one
+    /// closest to the session's rate, or else the device's own, with the fewest channels the
+    /// session needs and float samples over integer ones.
+    pub fn preferred_config(
+        &self,
+    ) -> Result<(Arc<cpal::Device>, cpal::SupportedStreamConfig), Error> {
+        let (device, config, rank) =
+            Self::first_device_best(self.ranked_configs()?).ok_or(Error::NoSupportedConfigs)?;
+        log::info!(
+            "Selected {} Hz, {} channels, {:?} samples on {} ({:?} rate)",
+            config.sample_rate().0,
+            config.channels(),
+            config.sample_format(),
+            device.name().unwrap_or_default(),
+            rank.rate,
+        );
+        Ok((device, config))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn range(
+        channels: u16,
+        rates: (u32, u32),
+        format: cpal::SampleFormat,
+    ) -> cpal::SupportedStreamConfigRange {
+        cpal::SupportedStreamConfigRange::new(
+            channels,
+            cpal::SampleRate(rates.0),
+            cpal::SampleRate(rates.1),
+            cpal::SupportedBufferSize::Unknown,
+            format,
+        )
+    }
+
+    fn best(
+        request: ConfigRequest,
+        ranges: &[cpal::SupportedStreamConfigRange],
+    ) -> Option<(usize, u32)> {
+        ranges
+            .iter()
+            .enumerate()
+            .filter_map(|(i, r)| request.rank(r).map(|(rate, rank)| (rank, i, rate)))
+            .min()
+            .map(|(_, i, rate)| (i, rate))
+    }
+
+    #[test]
+    fn test_report_uses_output_rate() {
+        let config = StreamConfig {
+            channels: 2,
+            sample_rate: cpal::SampleRate(48_000),
+            buffer_size: cpal::BufferSize::Default,
+        };
+        let started = SystemTime::UNIX_EPOCH + Duration::from_secs(1);
+        let metadata = Metadata::new("Mic".to_string(), config)
+            .with_sample_format(cpal::SampleFormat::I16)
+            .with_times(started, started + Duration::from_secs(3));
+
+        // Three seconds of 16 kHz output, which would read as one second at the device rate
+        let report = metadata.report(48_000);
+        assert_eq!(report.duration(), Duration::from_secs(3));
+        assert_eq!(report.started_at(), Some(started));
+
+        let expected = r#"{"input":{"device":"Mic","channels":2,"sample_rate":48000,"buffer_size":null,"sample_format":"i16"},"output_rate":16000,"samples":48000,"duration_ms":3000,"started_at":1000,"stopped_at":4000}"#;
+        assert_eq!(serde_json::to_string(&report).unwrap(), expected);
+        assert_eq!(
+            serde_json::from_str::<RecordingInfo>(expected).unwrap(),
+            report
+        );
+    }
+
+    #[test]
+    fn test_requested_rate_is_used() {
+        let request = ConfigRequest {
+            sample_rate: Some(44_100),
+            native_rate: Some(48_000),
+            min_channels: 1,
+        };
+        let (rate, rank) = request
+            .rank(&range(2, (8_000, 96_000), cpal::SampleFormat::F32))
+            .unwrap();
+        assert_eq!(rate, 44_100);
+        assert_eq!(rank.rate, RateMatch::Requested);
+
+        assert!(request
+            .rank(&range(2, (48_000, 48_000), cpal::SampleFormat::F32))
+            .is_none());
+    }
+
+    #[test]
+    fn test_native_rate_preferred() {
+        let request = ConfigRequest {
+            sample_rate: None,
+            native_rate: Some(44_100),
+            min_channels: 1,
+        };
+        let ranges = [
+            range(1, (16_000, 16_000), cpal::SampleFormat::F32),
+            range(1, (8_000, 96_000), cpal::SampleFormat::I16),
+        ];
+        assert_eq!(best(request, &ranges), Some((1, 44_100)));
+
+        // Without a native rate, a multiple of the output rate
+        let request = ConfigRequest {
+            native_rate: None,
+            ..request
+        };
+        let ranges = [
+            range(1, (44_100, 44_100), cpal::SampleFormat::F32),
+            range(1, (22_050, 96_000), cpal::SampleFormat::F32),
+        ];
+        assert_eq!(best(request, &ranges), Some((1, 32_000)));
+    }
+
+    #[test]
+    fn test_fewest_channels_then_format() {
+        let request = ConfigRequest {
+            sample_rate: None,
+            native_rate: Some(48_000),
+            min_channels: 2,
+        };
+        let ranges = [
+            range(1, (48_000, 48_000), cpal::SampleFormat::F32),
+            range(4, (48_000, 48_000), cpal::SampleFormat::F32),
+            range(2, (48_000, 48_000), cpal::SampleFormat::I16),
+            range(2, (48_000, 48_000), cpal::SampleFormat::F32),
+        ];
+        assert_eq!(best(request, &ranges), Some((3, 48_000)));
+    }
+
+    #[test]
+    fn test_float_formats_rank_first() {
+        use cpal::SampleFormat::{F32, I16, U16};
+        let mut formats = [U16, F32, I16];
+        formats.sort_by_key(|f| format_rank(*f));
+        assert_eq!(formats, [F32, I16, U16]);
+    }
+
+    #[test]
+    fn test_preferred_config_stays_on_first_device() {
+        let rank = |channels, format| ConfigRank {
+            rate: RateMatch::Native,
+            channels,
+            format,
+        };
+        let (first, second) = (Arc::new("first"), Arc::new("second"));
+        let configs = vec![
+            (first.clone(), 'a', rank(2, 1)),
+            (first.clone(), 'b', rank(1, 1)),
+            (first.clone(), 'c', rank(1, 1)),
+            (second.clone(), 'd', rank(1, 0)),
+        ];
+
+        let (device, config, _) = Session::first_device_best(configs).unwrap();
+        assert!(Arc::ptr_eq(&device, &first));
+        assert_eq!(config, 'b');
+
+        let configs = vec![(second.clone(), 'd', rank(1, 0))];
+        assert_eq!(Session::first_device_best(configs).unwrap().1, 'd');
+        assert!(Session::first_device_best(Vec::<(Arc<()>, (), ConfigRank)>::new()).is_none());
+    }
+
+    #[test]
+    fn test_low_rate_device_still_usable() {
+        let request = ConfigRequest {
+            sample_rate: None,
+            native_rate: None,
+            min_channels: 1,
+        };
+        let (rate, rank) = request
+            .rank(&range(1, (8_000, 11_025), cpal::SampleFormat::I16))
+            .unwrap();
+        assert_eq!(rate, 11_025);
+        assert_eq!(rank.rate, RateMatch::Lower);
+    }
 }
@@ -0,0 +1,302 @@
+use std::f64::consts::PI;
+
+/// Zero crossings of the sinc kernel kept on each side of its centre, measured at the lower of
+/// the two rates. More means a sharper cutoff and more work per output sample.
+const ZERO_CROSSINGS: f64 = 16.0;
+
+/// Cutoff relative to the lower Nyquist frequency, leaving room for the transition band.
+const ROLLOFF: f64 = 0.95;
+
+/// Streaming polyphase resampler.
+///
+/// The ratio between the two rates is reduced to `up / down`, and a windowed-sinc kernel is
+/// precomputed for each of the `up` fractional positions an output sample can fall on. Input
+/// history carries over between calls to [`Resampler::process`], so splitting a signal into
+/// callbacks of any size produces exactly the same output as processing it in one go.
+///
+/// Output sample `n` corresponds to time `n / to_hz`, with no group delay; samples are only
+/// emitted once enough input has arrived to cover the kernel on both sides, which costs
+/// `half_taps` input samples of latency. [`Resampler::finish`] flushes what's left.
+#[derive(Debug, Clone)]
+pub struct Resampler {
+    up: u64,
+    down: u64,
+    half_taps: usize,
+    /// `up` phases of `2 * half_taps` coefficients each
+    taps: Vec<f32>,
+    history: Vec<f32>,
+    /// Absolute input index of `history[0]`; negative while the initial zero padding remains
+    start: i64,
+    next_output: u64,
+}
+
+impl Resampler {
+    /// # Panics
+    ///
+    /// when either rate is zero
+    #[must_use]
+    #[allow(clippy::cast_possible_wrap)]
+    pub fn new(from_hz: u32, to_hz: u32) -> Self {
+        assert!(from_hz > 0 && to_hz > 0);
+
+        let gcd = gcd(from_hz, to_hz);
+        let up = u64::from(to_hz / gcd);
+        let down = u64::from(from_hz / gcd);
+
+        let (half_taps, taps) = if up == down {
+            (1, vec![1.0, 0.0])
+        } else {
+            Self::kernel(up, down)
+        };
+
+        Self {
+            up,
+            down,
+            half_taps,
+            taps,
+            history: vec![0.0; half_taps - 1],
+            start: 1 - half_taps as i64,
+            next_output: 0,
+        }
+    }
+
+    #[allow(clippy::cast_possible_truncation)]
+    #[allow(clippy::cast_precision_loss)]
+    #[allow(clippy::cast_sign_loss)]
+    fn kernel(up: u64, down: u64) -> (usize, Vec<f32>) {
+        let cutoff = (up as f64 / down as f64).min(1.0) * ROLLOFF;
+        let half_taps = (ZERO_CROSSINGS / cutoff).ceil() as usize;
+        let width = 2 * half_taps;
+        let half = half_taps as f64;
+
+        let mut taps = Vec::with_capacity(up as usize * width);
+        for phase in 0..up {
+            let frac = phase as f64 / up as f64;
+            let coefficients: Vec<f64> = (0..width)
+                .map(|j| {
+                    let d = frac + half - 1.0 - j as f64;
+                    cutoff * sinc(cutoff * d) * blackman(d / half)
+                })
+                .collect();
+
+            // Normalising each phase separately keeps DC gain exact whatever the fraction
+            let sum: f64 = coefficients.iter().sum();
+            taps.extend(coefficients.iter().map(|c| (c / sum) as f32));
+        }
+
+        (half_taps, taps)
+    }
+
+    /// Number of output samples that `input_len` input samples produce once flushed.
+    #[must_use]
+    pub fn output_len(&self, input_len: u64) -> u64 {
+        (input_len * self.up).div_ceil(self.down)
+    }
+
+    /// Resamples `input`, appending every output sample that can be computed so far to `out`.
+    pub fn process(&mut self, input: &[f32], out: &mut Vec<f32>) {
+        self.history.extend_from_slice(input);
+        self.drain(None, out);
+    }
+
+    /// Pads the input with silence to emit the trailing samples held back by
+    /// [`Resampler::process`], then resets so the next call starts a new signal.
+    #[allow(clippy::cast_possible_wrap)]
+    pub fn finish(&mut self, out: &mut Vec<f32>) {
+        let end = self.end();
+        let limit = u64::try_from(end).unwrap_or_default() * self.up;
+
+        self.history
+            .extend(std::iter::repeat_n(0.0, self.half_taps + 1));
+        self.drain(Some(limit), out);
+
+        *self = Self {
+            history: vec![0.0; self.half_taps - 1],
+            start: 1 - self.half_taps as i64,
+            next_output: 0,
+            taps: std::mem::take(&mut self.taps),
+            ..*self
+        };
+    }
+
+    #[allow(clippy::cast_possible_wrap)]
+    fn end(&self) -> i64 {
+        self.start + self.history.len() as i64
+    }
+
+    #[allow(clippy::cast_possible_wrap)]
+    #[allow(clippy::cast_possible_truncation)]
+    #[allow(clippy::cast_sign_loss)]
+    fn drain(&mut self, limit: Option<u64>, out: &mut Vec<f32>) {
+        let width = 2 * self.half_taps;
+        let end = self.end();
+
+        loop {
+            let position = self.next_output * self.down;
+            if limit.is_some_and(|l| position >= l) {
+                break;
+            }
+
+            let centre = (position / self.up) as i64;
+            if centre + self.half_taps as i64 >= end {
+                break;
+            }
+
+            let phase = (position % self.up) as usize;
+            let first = (centre + 1 - self.half_taps as i64 - self.start) as usize;
+
+            let taps = &self.taps[phase * width..(phase + 1) * width];
+            let window = &self.history[first..first + width];
+            out.push(taps.iter().zip(window).map(|(t, x)| t * x).sum());
+
+            self.next_output += 1;
+        }
+
+        // Drop input that no future output sample can reach
+        let centre = ((self.next_output * self.down) / self.up) as i64;
+        let keep_from = (centre + 1 - self.half_taps as i64).min(end);
+        if keep_from > self.start {
+            self.history.drain(..(keep_from - self.start) as usize);
+            self.start = keep_from;
+        }
+    }
+}
+
+fn gcd(mut a: u32, mut b: u32) -> u32 {
+    while b != 0 {
+        (a, b) = (b, a % b);
+    }
+    a
+}
+
+fn sinc(x: f64) -> f64 {
+    if x == 0.0 {
+        1.0
+    } else {
+        (PI * x).sin() / (PI * x)
+    }
+}
+
+/// Blackman window over `[-1, 1]`.
+fn blackman(x: f64) -> f64 {
+    if x.abs() >= 1.0 {
+        0.0
+    } else {
+        0.42 + 0.5 * (PI * x).cos() + 0.08 * (2.0 * PI * x).cos()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Sweep range, covering the speech band whisper relies on.
+    const SWEEP_FROM: f64 = 50.0;
+    const SWEEP_TO: f64 = 6_000.0;
+    const SWEEP_SECS: f64 = 1.0;
+
+    /// Samples at either end left out of comparisons, where the signal is truncated.
+    const EDGE: usize = 256;
+
+    /// Reference linear sweep at time `t`.
+    fn sweep(t: f64) -> f64 {
+        let k = (SWEEP_TO - SWEEP_FROM) / SWEEP_SECS;
+        (2.0 * PI * (SWEEP_FROM * t + 0.5 * k * t * t)).sin()
+    }
+
+    fn tone(hz: f64) -> impl Fn(f64) -> f64 {
+        move |t| (2.0 * PI * hz * t).sin()
+    }
+
+    #[allow(clippy::cast_possible_truncation)]
+    #[allow(clippy::cast_precision_loss)]
+    #[allow(clippy::cast_sign_loss)]
+    fn sampled(f: impl Fn(f64) -> f64, rate: u32, secs: f64) -> Vec<f32> {
+        let n = (f64::from(rate) * secs) as usize;
+        (0..n)
+            .map(|i| f(i as f64 / f64::from(rate)) as f32)
+            .collect()
+    }
+
+    fn resample_in_chunks(from_hz: u32, to_hz: u32, input: &[f32], chunk: usize) -> Vec<f32> {
+        let mut resampler = Resampler::new(from_hz, to_hz);
+        let mut out = Vec::new();
+        for c in input.chunks(chunk) {
+            resampler.process(c, &mut out);
+        }
+        resampler.finish(&mut out);
+        out
+    }
+
+    /// Ratio in dB of the reference's energy to that of the output's deviation from it.
+    #[allow(clippy::cast_precision_loss)]
+    fn snr_db(output: &[f32], rate: u32, reference: impl Fn(f64) -> f64) -> f64 {
+        let (signal, noise) = output[EDGE..output.len() - EDGE]
+            .iter()
+            .enumerate()
+            .map(|(i, y)| {
+                let expected = reference((i + EDGE) as f64 / f64::from(rate));
+                (expected, f64::from(*y))
+            })
+            .fold((0.0, 0.0), |(s, n), (expected, y)| {
+                (s + expected * expected, n + (y - expected).powi(2))
+            });
+        10.0 * (signal / noise).log10()
+    }
+
+    #[test]
+    fn test_sweep_quality() {
+        for from_hz in [22_050, 44_100, 48_000, 96_000] {
+            let input = sampled(sweep, from_hz, SWEEP_SECS);
+            let output = resample_in_chunks(from_hz, 16_000, &input, 441);
+            let snr = snr_db(&output, 16_000, sweep);
+            assert!(snr > 70.0, "{from_hz} Hz sweep SNR {snr:.1} dB");
+        }
+    }
+
+    #[test]
+    fn test_upsample_quality() {
+        let input = sampled(tone(440.0), 8_000, 1.0);
+        let output = resample_in_chunks(8_000, 16_000, &input, 160);
+        let snr = snr_db(&output, 16_000, tone(440.0));
+        assert!(snr > 70.0, "8 kHz tone SNR {snr:.1} dB");
+    }
+
+    #[test]
+    fn test_rejects_aliases() {
+        // 10 kHz can't be represented at 16 kHz and would otherwise fold back to 6 kHz
+        let input = sampled(tone(10_000.0), 48_000, 1.0);
+        let output = resample_in_chunks(48_000, 16_000, &input, 512);
+        let kept = &output[EDGE..output.len() - EDGE];
+        #[allow(clippy::cast_precision_loss)]
+        let power = kept.iter().map(|y| f64::from(*y).powi(2)).sum::<f64>() / kept.len() as f64;
+        // Relative to the input tone's power of 1/2
+        let leak = 10.0 * (power / 0.5).log10();
+        assert!(leak < -60.0, "alias at {leak:.1} dB");
+    }
+
+    #[test]
+    fn test_chunking_is_seamless() {
+        let input = sampled(sweep, 48_000, 0.25);
+        let whole = resample_in_chunks(48_000, 16_000, &input, input.len());
+        for chunk in [1, 7, 128, 441, 1024] {
+            assert_eq!(whole, resample_in_chunks(48_000, 16_000, &input, chunk));
+        }
+    }
+
+    #[test]
+    fn test_output_len() {
+        for (from_hz, len) in [(44_100, 44_100), (48_000, 1000), (16_000, 123), (8_000, 77)] {
+            let input = vec![0.5; len];
+            let expected = Resampler::new(from_hz, 16_000).output_len(len as u64);
+            let output = resample_in_chunks(from_hz, 16_000, &input, 256);
+            assert_eq!(output.len() as u64, expected, "{from_hz} Hz");
+        }
+    }
+
+    #[test]
+    fn test_identity() {
+        let input = sampled(sweep, 16_000, 0.1);
+        assert_eq!(resample_in_chunks(16_000, 16_000, &input, 100), input);
+    }
+}
@@ -1,13 +1,14 @@
 use std::{
     iter::{Chain, Copied, RepeatN},
+    ops::Range,
     slice::Iter,
     time::Duration,
 };
 
+use serde::{Deserialize, Serialize};
 use thiserror::Error;
 use webrtc_vad::{Vad, VadMode as BadVadMode};
 
-#[allow(dead_code)]
 #[derive(Debug, Clone, Copy)]
 pub enum SampleSize {
     Small = 10,
@@ -15,11 +16,37 @@ pub enum SampleSize {
     Large = 30,
 }
 
-#[derive(Debug, Clone, Copy)]
+impl TryFrom<u32> for SampleSize {
+    type Error = Error;
+
+    fn try_from(ms: u32) -> Result<Self, Self::Error> {
+        match ms {
+            10 => Ok(Self::Small),
+            20 => Ok(Self::Medium),
+            30 => Ok(Self::Large),
+            _ => Err(Error::BadFrameSize(ms)),
+        }
+    }
+}
+
+/// Parses `--vad-frame-ms`, which the detector only supports at 10, 20 or 30.
+pub fn parse_frame_ms(s: &str) -> Result<u32, String> {
+    let ms: u32 = s.parse().map_err(|e| format!("{e}"))?;
+    SampleSize::try_from(ms)
+        .map(|_| ms)
+        .map_err(|e| e.to_string())
+}
+
+/// How reluctant the detector is to call audio speech, from least to most.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, clap::ValueEnum, Deserialize, Serialize)]
 pub enum Mode {
+    #[serde(rename = "quality")]
     Quality = 0,
+    #[serde(rename = "low_bitrate")]
     LowBitrate = 1,
+    #[serde(rename = "aggressive")]
     Aggressive = 2,
+    #[serde(rename = "very_aggressive")]
     VeryAggressive = 3,
 }
 
@@ -41,6 +68,13 @@ pub struct Config {
 
     sample_size: SampleSize,
     resolution: Duration,
+
+    /// Speech needed before a [`VoiceDetector`] starts a segment of it
+    onset: Duration,
+    /// Silence needed before a [`VoiceDetector`] ends a segment of speech
+    release: Duration,
+    /// RMS below which a frame never counts as speech, whatever the VAD makes of it
+    energy_threshold: f32,
 }
 
 impl Default for Config {
@@ -51,6 +85,10 @@ impl Default for Config {
 
             sample_size: SampleSize::Medium,
             resolution: Duration::from_millis(200),
+
+            onset: Duration::from_millis(40),
+            release: Duration::from_millis(200),
+            energy_threshold: 0.003,
         }
     }
 }
@@ -60,6 +98,129 @@ impl Config {
     pub fn samples_per_frame(&self) -> usize {
         self.resolution.as_millis() as usize / self.sample_size as usize
     }
+
+    #[must_use]
+    pub fn with_mode(mut self, mode: Mode) -> Self {
+        self.mode = mode;
+        self
+    }
+
+    #[must_use]
+    pub fn with_sample_size(mut self, size: SampleSize) -> Self {
+        self.sample_size = size;
+        self
+    }
+
+    /// Window that [`Self::detect_voice`] counts voiced frames over.
+    #[must_use]
+    pub fn with_resolution(mut self, resolution: Duration) -> Self {
+        self.resolution = resolution;
+        self
+    }
+
+    #[must_use]
+    pub fn with_onset(mut self, onset: Duration) -> Self {
+        self.onset = onset;
+        self
+    }
+
+    #[must_use]
+    pub fn with_release(mut self, release: Duration) -> Self {
+        self.release = release;
+        self
+    }
+
+    /// Level in dBFS that a frame has to reach to count as speech.
+    #[must_use]
+    pub fn with_energy_threshold(mut self, db: f32) -> Self {
+        self.energy_threshold = 10_f32.powf(db / 20.0);
+        self
+    }
+
+    #[must_use]
+    pub fn frame_duration(&self) -> Duration {
+        Duration::from_millis(self.sample_size as u64)
+    }
+}
+
+/// Adjustments to voice activity detection, for a session or the daemon's default. Anything
+/// left unset keeps the detector's default.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, clap::Args, Deserialize, Serialize)]
+#[serde(default)]
+pub struct Tuning {
+    /// How reluctant voice activity detection is to call audio speech
+    #[clap(long = "vad-mode", value_enum)]
+    mode: Option<Mode>,
+
+    /// Length of audio the detector judges at a time, in milliseconds: 10, 20 or 30
+    #[clap(long = "vad-frame-ms", value_parser = parse_frame_ms)]
+    frame_ms: Option<u32>,
+
+    /// Speech needed to count as the start of speech when trimming or listening for the wake
+    /// phrase, in milliseconds (40 by default)
+    #[clap(long = "vad-onset-ms")]
+    onset_ms: Option<u32>,
+
+    /// Silence needed to count as the end of speech when trimming or listening for the wake
+    /// phrase, in milliseconds (200 by default)
+    #[clap(long = "vad-release-ms")]
+    release_ms: Option<u32>,
+
+    /// Frames quieter than this never count as speech, in dBFS (-50 by default). When the
+    /// detector can't judge a frame, this alone decides
+    #[clap(long = "vad-energy-db", allow_negative_numbers = true)]
+    energy_db: Option<i32>,
+}
+
+impl Tuning {
+    #[must_use]
+    pub fn with_mode(mut self, mode: Mode) -> Self {
+        self.mode = Some(mode);
+        self
+    }
+
+    #[must_use]
+    pub fn with_onset_ms(mut self, ms: u32) -> Self {
+        self.onset_ms = Some(ms);
+        self
+    }
+
+    #[must_use]
+    pub fn with_release_ms(mut self, ms: u32) -> Self {
+        self.release_ms = Some(ms);
+        self
+    }
+
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        *self == Self::default()
+    }
+
+    /// The default configuration with these adjustments made. A frame length the detector
+    /// doesn't support is logged and left at the default.
+    #[must_use]
+    #[allow(clippy::cast_precision_loss)]
+    pub fn config(&self) -> Config {
+        let mut config = Config::default();
+        if let Some(mode) = self.mode {
+            config = config.with_mode(mode);
+        }
+        match self.frame_ms.map(SampleSize::try_from) {
+            Some(Ok(size)) => config = config.with_sample_size(size),
+            Some(Err(e)) => log::warn!("Ignoring VAD frame length: {e}"),
+            None => (),
+        }
+        if let Some(ms) = self.onset_ms {
+            config = config.with_onset(Duration::from_millis(ms.into()));
+        }
+        if let Some(ms) = self.release_ms {
+            config = config.with_release(Duration::from_millis(ms.into()));
+        }
+        if let Some(db) = self.energy_db {
+            config = config.with_energy_threshold(db as f32);
+        }
+        config
+    }
 }
 
 impl TryFrom<&Config> for Vad {
@@ -84,6 +245,9 @@ pub enum Error {
 
     #[error("couldn't convert the sample rate into an acceptable value")]
     BadSampleRate,
+
+    #[error("frames of {0}ms aren't supported, only 10, 20 or 30")]
+    BadFrameSize(u32),
 }
 
 pub struct Buffer<'a> {
@@ -92,20 +256,34 @@ pub struct Buffer<'a> {
     index: usize,
 }
 
-impl Buffer<'_> {
+impl<'a> Buffer<'a> {
     fn size(&self) -> usize {
         self.size
     }
 
-    fn is_voice_segment(&self, vad: &mut Vad) -> Result<bool, Error> {
-        vad.is_voice_segment(self.as_ref()).or(Err(Error::Vad))
+    /// The samples of this frame, which fall short of a full one if `data` runs out.
+    fn frame(&self) -> &'a [i16] {
+        let start = (self.index * self.size()).min(self.data.len());
+        let end = (start + self.size()).min(self.data.len());
+        &self.data[start..end]
+    }
+
+    /// Judges the frame, padded out with silence if it's short.
+    fn judge(self, vad: &mut Vad) -> Result<bool, Error> {
+        let frame = self.frame();
+        if frame.len() == self.size() {
+            vad.is_voice_segment(frame)
+        } else {
+            vad.is_voice_segment(&self.into_iter().collect::<Vec<_>>())
+        }
+        .or(Err(Error::Vad))
     }
 }
 
 impl Config {
     #[must_use]
     pub fn buffer_size(&self) -> usize {
-        (self.sample_size as u16 * self.sample_rate / 1000) as usize
+        self.sample_size as usize * usize::from(self.sample_rate) / 1000
     }
 
     #[must_use]
@@ -120,9 +298,7 @@ impl Config {
 
 impl<'a> AsRef<[i16]> for Buffer<'a> {
     fn as_ref(&self) -> &'a [i16] {
-        let start = self.index * self.size();
-        let end = start + self.size();
-        self.data[start..end].as_ref()
+        self.frame()
     }
 }
 
@@ -131,8 +307,9 @@ impl<'a> IntoIterator for Buffer<'a> {
     type IntoIter = Chain<Copied<Iter<'a, i16>>, RepeatN<i16>>;
 
     fn into_iter(self) -> Self::IntoIter {
-        let fill = self.size() - self.data.len();
-        self.data
+        let frame = self.frame();
+        let fill = self.size() - frame.len();
+        frame
             .iter()
             .copied()
             .chain(std::iter::repeat_n(0_i16, fill))
@@ -146,8 +323,8 @@ impl Config {
         let mut vad: Vad = self.try_into()?;
         let data = input.as_ref();
 
-        let output: Result<Vec<_>, _> = (0..data.len() / self.buffer_size())
-            .map(|i| self.buffer_from(i, data).is_voice_segment(&mut vad))
+        let output: Result<Vec<_>, _> = (0..data.len().div_ceil(self.buffer_size()))
+            .map(|i| self.buffer_from(i, data).judge(&mut vad))
             .collect();
 
         Ok(output?
@@ -156,3 +333,325 @@ impl Config {
             .collect::<Vec<_>>())
     }
 }
+
+impl Config {
+    /// The part of `audio` from the start of the first segment of speech to the end of the
+    /// last, widened by `margin` on either side, or `None` when there's no speech at all.
+    #[allow(clippy::cast_possible_truncation)]
+    #[must_use]
+    pub fn speech_range(&self, audio: &[f32], margin: Duration) -> Option<Range<usize>> {
+        let mut detector = VoiceDetector::new(self);
+        let mut segments = detector.push(audio);
+        segments.extend(detector.finish());
+
+        let mut speech = segments.iter().filter(|s| s.is_speech());
+        let first = speech.next()?;
+        let last = speech.next_back().unwrap_or(first);
+
+        let samples = |d: Duration| d.as_millis() as usize * usize::from(self.sample_rate) / 1000;
+        let start = samples(first.start().saturating_sub(margin));
+        let end = samples(last.end() + margin).min(audio.len());
+        Some(start..end)
+    }
+}
+
+/// Feeds live audio to a VAD one frame at a time, carrying partial frames over between blocks.
+///
+/// Owns a VAD instance, which can't be sent between threads, so this and everything built on
+/// it has to stay on the thread that created it.
+pub struct Frames {
+    /// `None` when the VAD couldn't be set up, leaving energy to decide alone
+    vad: Option<Vad>,
+    size: usize,
+    frame_duration: Duration,
+    energy_threshold: f32,
+    /// Samples that don't yet fill a frame
+    partial: Vec<i16>,
+}
+
+impl Frames {
+    #[must_use]
+    pub fn new(config: &Config) -> Self {
+        let size = config.buffer_size();
+        Self {
+            vad: Vad::try_from(config)
+                .inspect_err(|e| log::warn!("Judging speech by energy alone: {e}"))
+                .ok(),
+            size,
+            frame_duration: config.frame_duration(),
+            energy_threshold: config.energy_threshold,
+            partial: Vec::with_capacity(size),
+        }
+    }
+
+    /// Samples per frame.
+    #[must_use]
+    pub fn size(&self) -> usize {
+        self.size
+    }
+
+    #[must_use]
+    pub fn frame_duration(&self) -> Duration {
+        self.frame_duration
+    }
+
+    /// Whether each frame `block` completes is voiced.
+    pub fn push(&mut self, block: &[f32]) -> Vec<bool> {
+        let mut voiced = Vec::new();
+        for sample in block {
+            self.partial.push(<i16 as cpal::Sample>::from(sample));
+            if self.partial.len() == self.size {
+                voiced.push(self.judge());
+            }
+        }
+        voiced
+    }
+
+    /// Judges the samples left over once the audio has ended as one last frame, padded out
+    /// with silence, if there are any.
+    pub fn finish(&mut self) -> Option<bool> {
+        if self.partial.is_empty() {
+            return None;
+        }
+        self.partial.resize(self.size, 0);
+        Some(self.judge())
+    }
+
+    /// Judges the full frame waiting in `partial`. A frame has to be loud enough and, if the
+    /// VAD can judge it, sound like speech.
+    #[allow(clippy::cast_possible_truncation, clippy::cast_precision_loss)]
+    fn judge(&mut self) -> bool {
+        let sum_squares: f64 = self.partial.iter().map(|&s| f64::from(s).powi(2)).sum();
+        let rms = (sum_squares / self.partial.len() as f64).sqrt() / f64::from(i16::MAX);
+        let voiced = rms as f32 >= self.energy_threshold
+            && self
+                .vad
+                .as_mut()
+                .is_none_or(|vad| vad.is_voice_segment(&self.partial).unwrap_or(true));
+        self.partial.clear();
+        voiced
+    }
+}
+
+/// A stretch of audio that's all speech or all not, as judged by a [`VoiceDetector`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Segment {
+    speech: bool,
+    start: Duration,
+    end: Duration,
+}
+
+impl Segment {
+    #[must_use]
+    pub fn is_speech(&self) -> bool {
+        self.speech
+    }
+
+    /// How far into the audio the segment starts.
+    #[must_use]
+    pub fn start(&self) -> Duration {
+        self.start
+    }
+
+    #[must_use]
+    pub fn end(&self) -> Duration {
+        self.end
+    }
+
+    #[must_use]
+    pub fn duration(&self) -> Duration {
+        self.end.saturating_sub(self.start)
+    }
+}
+
+/// Follows a stream of audio block by block, smoothing the VAD's frame-by-frame decisions
+/// into segments of speech and non-speech. Speech has to last the configured onset to start
+/// a segment of it, and silence the release to end one, so a click or a breath doesn't flip
+/// it back and forth.
+///
+/// Bound to its thread by the [`Frames`] it reads.
+pub struct VoiceDetector {
+    frames: Frames,
+    onset: u32,
+    release: u32,
+    speaking: bool,
+    /// Frames in a row that disagree with `speaking`
+    run: u32,
+    /// Frames judged so far
+    position: u32,
+    /// Frame the current segment started at
+    start: u32,
+}
+
+impl VoiceDetector {
+    #[must_use]
+    pub fn new(config: &Config) -> Self {
+        let frames = Frames::new(config);
+        let frame_ms = frames.frame_duration().as_millis();
+        let count = |d: Duration| {
+            u32::try_from(d.as_millis().div_ceil(frame_ms))
+                .unwrap_or(u32::MAX)
+                .max(1)
+        };
+        Self {
+            onset: count(config.onset),
+            release: count(config.release),
+            frames,
+            speaking: false,
+            run: 0,
+            position: 0,
+            start: 0,
+        }
+    }
+
+    /// Whether the segment in progress is speech.
+    #[must_use]
+    pub fn is_speaking(&self) -> bool {
+        self.speaking
+    }
+
+    /// Accounts for the next block of audio, returning the segments it brings to an end.
+    pub fn push(&mut self, block: &[f32]) -> Vec<Segment> {
+        let voiced = self.frames.push(block);
+        voiced.into_iter().filter_map(|v| self.frame(v)).collect()
+    }
+
+    /// Ends the segments still open once the audio has ended.
+    #[must_use]
+    pub fn finish(mut self) -> Vec<Segment> {
+        let mut segments: Vec<_> = self
+            .frames
+            .finish()
+            .and_then(|v| self.frame(v))
+            .into_iter()
+            .collect();
+        segments.extend(self.segment(self.position));
+        segments
+    }
+
+    fn frame(&mut self, voiced: bool) -> Option<Segment> {
+        self.position += 1;
+        if voiced == self.speaking {
+            self.run = 0;
+            return None;
+        }
+
+        self.run += 1;
+        let needed = if self.speaking {
+            self.release
+        } else {
+            self.onset
+        };
+        if self.run < needed {
+            return None;
+        }
+        // The change happened where the run began
+        let boundary = self.position - self.run;
+        let ended = self.segment(boundary);
+        self.speaking = voiced;
+        self.start = boundary;
+        self.run = 0;
+        ended
+    }
+
+    /// The segment in progress, ended at frame `end`, unless it would be empty.
+    fn segment(&self, end: u32) -> Option<Segment> {
+        let frame = self.frames.frame_duration();
+        (end > self.start).then(|| Segment {
+            speech: self.speaking,
+            start: frame * self.start,
+            end: frame * end,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn tone(samples: usize) -> Vec<f32> {
+        quiet_tone(samples, 0.5)
+    }
+
+    #[allow(clippy::cast_precision_loss)]
+    fn quiet_tone(samples: usize, amplitude: f32) -> Vec<f32> {
+        (0..samples)
+            .map(|i| amplitude * (2.0 * std::f32::consts::PI * 440.0 * i as f32 / 16_000.0).sin())
+            .collect()
+    }
+
+    #[test]
+    fn test_speech_range_trims_silence() {
+        let mut audio = vec![0.0; 32_000];
+        audio.extend(tone(16_000));
+        audio.extend(vec![0.0; 32_000]);
+
+        let range = Config::default()
+            .speech_range(&audio, Duration::from_millis(100))
+            .unwrap();
+        // Speech is located to within a 200ms window, then widened by the 100ms margin
+        let (start, end) = (32_000 - 1600, 48_000 + 1600);
+        assert!((start - 3200..=start).contains(&range.start));
+        assert!((end..=end + 3200).contains(&range.end));
+    }
+
+    #[test]
+    fn test_segments_are_smoothed() {
+        let mut detector = VoiceDetector::new(&Config::default());
+        let mut audio = vec![0.0; 8000];
+        audio.extend(tone(8000));
+        // A 100ms gap is shorter than the release, so speech carries on through it
+        audio.extend(vec![0.0; 1600]);
+        audio.extend(tone(8000));
+        // A single 20ms click is shorter than the onset
+        audio.extend(vec![0.0; 8000]);
+        audio.extend(tone(320));
+        audio.extend(vec![0.0; 7680]);
+
+        // Fed in uneven blocks, to carry partial frames over
+        let mut segments: Vec<_> = audio.chunks(999).flat_map(|b| detector.push(b)).collect();
+        segments.extend(detector.finish());
+
+        let ms = Duration::from_millis;
+        let speech: Vec<_> = segments.iter().filter(|s| s.is_speech()).collect();
+        assert_eq!(speech.len(), 1);
+        assert_eq!((speech[0].start(), speech[0].end()), (ms(500), ms(1600)));
+        assert_eq!(segments.first().unwrap().start(), Duration::ZERO);
+        assert_eq!(segments.last().unwrap().end(), ms(2600));
+    }
+
+    #[test]
+    fn test_quiet_frames_are_not_speech() {
+        let config = Config::default().with_energy_threshold(-20.0);
+        let margin = Duration::ZERO;
+        // Around -29dBFS, below the threshold even though the VAD may call it speech
+        assert_eq!(config.speech_range(&quiet_tone(16_000, 0.05), margin), None);
+        assert!(config.speech_range(&tone(16_000), margin).is_some());
+
+        let mut frames = Frames::new(&config);
+        assert!(frames.push(&quiet_tone(3200, 0.05)).iter().all(|&v| !v));
+        assert!(frames.push(&tone(3200)).iter().all(|&v| v));
+    }
+
+    #[test]
+    fn test_partial_trailing_frame() {
+        let config = Config::default();
+        let data = vec![1_i16; 700];
+        let last = config.buffer_from(2, &data);
+        assert_eq!(last.as_ref().len(), 60);
+        let padded: Vec<_> = last.into_iter().collect();
+        assert_eq!(padded.len(), 320);
+        assert_eq!(padded.iter().filter(|&&s| s == 1).count(), 60);
+
+        assert_eq!(config.buffer_from(5, &data).as_ref(), &[] as &[i16]);
+        assert_eq!(config.detect_voice(&data).unwrap(), vec![0]);
+    }
+
+    #[test]
+    fn test_no_speech() {
+        let config = Config::default();
+        let silence = vec![0.0; 48_000];
+        assert_eq!(config.speech_range(&silence, Duration::ZERO), None);
+        assert_eq!(config.speech_range(&[], Duration::ZERO), None);
+    }
+}
@@ -3,6 +3,7 @@
 #![allow(clippy::missing_errors_doc)]
 
 pub mod app;
+pub mod archive;
 pub mod audio;
 pub mod config;
 pub mod socket;
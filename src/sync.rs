@@ -1,6 +1,9 @@
-use std::thread::{self, JoinHandle};
+use std::{
+    sync::{Arc, Mutex},
+    thread::{self, JoinHandle},
+};
 
-use crossbeam::channel::{self, unbounded, Sender};
+use crossbeam::channel::{self, bounded, unbounded, Receiver, Sender, TrySendError};
 
 type Reduction<T, R> = Box<dyn Fn(channel::Iter<T>) -> R + Send>;
 
@@ -42,3 +45,82 @@ where
         )
     }
 }
+
+/// Hands each value to every current subscriber. A subscriber that falls behind misses values
+/// rather than holding up the sender, and one that has gone away is forgotten.
+pub struct Broadcast<T> {
+    subscribers: Arc<Mutex<Vec<Sender<T>>>>,
+}
+
+impl<T> Clone for Broadcast<T> {
+    fn clone(&self) -> Self {
+        Self {
+            subscribers: self.subscribers.clone(),
+        }
+    }
+}
+
+impl<T> Default for Broadcast<T> {
+    fn default() -> Self {
+        Self {
+            subscribers: Arc::new(Mutex::new(Vec::new())),
+        }
+    }
+}
+
+/// How many values a subscriber can fall behind by before it starts missing them.
+const BROADCAST_BACKLOG: usize = 256;
+
+impl<T: Clone> Broadcast<T> {
+    /// # Panics
+    ///
+    /// when the subscriber list's lock is poisoned
+    #[must_use]
+    pub fn subscribe(&self) -> Receiver<T> {
+        let (send, recv) = bounded(BROADCAST_BACKLOG);
+        self.subscribers.lock().unwrap().push(send);
+        recv
+    }
+
+    /// # Panics
+    ///
+    /// when the subscriber list's lock is poisoned
+    pub fn send(&self, value: &T) {
+        self.subscribers.lock().unwrap().retain(|s| {
+            !matches!(
+                s.try_send(value.clone()),
+                Err(TrySendError::Disconnected(_))
+            )
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_broadcast_reaches_every_subscriber() {
+        let broadcast = Broadcast::default();
+        let first = broadcast.subscribe();
+        let second = broadcast.subscribe();
+
+        broadcast.send(&1);
+        drop(second);
+        broadcast.send(&2);
+
+        assert_eq!(first.try_iter().collect::<Vec<_>>(), vec![1, 2]);
+        assert_eq!(broadcast.subscribers.lock().unwrap().len(), 1);
+    }
+
+    #[test]
+    fn test_slow_subscriber_misses_values() {
+        let broadcast = Broadcast::default();
+        let slow = broadcast.subscribe();
+        for i in 0..BROADCAST_BACKLOG + 10 {
+            broadcast.send(&i);
+        }
+        assert_eq!(slow.try_iter().count(), BROADCAST_BACKLOG);
+        assert_eq!(broadcast.subscribers.lock().unwrap().len(), 1);
+    }
+}
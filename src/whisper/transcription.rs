@@ -99,9 +99,13 @@ impl Job {
     }
 }
 
-#[derive(Debug, Clone, PartialEq)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type")]
 pub enum StrategyOpt {
+    #[serde(rename = "greedy")]
     Greedy { best_of: i32 },
+
+    #[serde(rename = "beam")]
     Beam { beam_size: i32, patience: f32 },
 }
 